@@ -0,0 +1,17 @@
+// A Shadertoy shader, opened as is. Channels are declared with the usual
+// option comments, named iChannel0 to iChannel3, e.g.
+//     texture(path=texture/noise.png, name=iChannel0, vflip=true)
+
+vec3 palette(float t) {
+    return 0.5 + 0.5 * cos(6.28318 * (t + vec3(0.0, 0.33, 0.67)));
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = (2.0 * fragCoord - iResolution.xy) / iResolution.y;
+    float d = length(uv) - 0.1 * sin(3.0 * iTime + 8.0 * atan(uv.y, uv.x));
+    vec3 col = palette(d + 0.2 * iTime) * (1.0 - smoothstep(0.0, 0.02, abs(d - 0.5)));
+    if (iMouse.z > 0.0) {
+        col += 0.2 * (1.0 - smoothstep(0.0, 8.0, length(fragCoord - iMouse.xy)));
+    }
+    fragColor = vec4(col, 1.0);
+}
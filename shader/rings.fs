@@ -0,0 +1,21 @@
+/*{
+    "DESCRIPTION": "Rings spreading from a point, an ISF shader opened as is",
+    "CREDIT": "shadey",
+    "ISFVSN": "2",
+    "CATEGORIES": ["Generator"],
+    "INPUTS": [
+        {"NAME": "speed", "TYPE": "float", "DEFAULT": 0.5, "MIN": 0.0, "MAX": 2.0},
+        {"NAME": "tint", "TYPE": "color", "DEFAULT": [1.0, 0.5, 0.0, 1.0]},
+        {"NAME": "center", "TYPE": "point2D", "DEFAULT": [0.5, 0.5], "MIN": [0.0, 0.0], "MAX": [1.0, 1.0]},
+        {"NAME": "invert", "TYPE": "bool", "DEFAULT": false},
+        {"NAME": "count", "TYPE": "long", "VALUES": [4, 8, 16], "LABELS": ["Few", "Some", "Many"], "DEFAULT": 8}
+    ]
+}*/
+
+void main() {
+    vec2 uv = isf_FragNormCoord - center;
+    uv.x *= RENDERSIZE.x / RENDERSIZE.y;
+    float ring = fract(length(uv) * float(count) - TIME * speed);
+    vec4 color = tint * smoothstep(0.0, 0.1, ring) * smoothstep(1.0, 0.6, ring);
+    gl_FragColor = invert ? vec4(1.0) - color : color;
+}
@@ -3,11 +3,21 @@ struct StdUniform {
     mouse_pos: vec2<u32>,
     time: f32,
     toggle_mouse_pos: vec2<u32>,
+    frame: u32,
+    delta_time: f32,
+    date: vec4<f32>,
+    mouse_buttons: vec4<u32>,
+    mouse_click: vec4<f32>,
+    scroll: vec2<f32>,
 };
 
 @group(0) @binding(0)
 var<uniform> _std_uniform: StdUniform;
 
+// 256x3, indexed by javascript key codes: held, pressed this frame, toggled
+@group(0) @binding(2)
+var _keyboard: texture_2d<f32>;
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) texcoords: vec2<f32>,
@@ -48,12 +58,50 @@ fn time() -> f32 {
     return _std_uniform.time;
 }
 
+fn frame() -> u32 {
+    return _std_uniform.frame;
+}
+
+fn delta_time() -> f32 {
+    return _std_uniform.delta_time;
+}
+
+// year, month (1-12), day, seconds since midnight (UTC)
+fn date() -> vec4<f32> {
+    return _std_uniform.date;
+}
+
+// 0: left, 1: right, 2: middle, 3: any other button
+fn mouse_down(button: u32) -> bool {
+    return _std_uniform.mouse_buttons[button] != 0u;
+}
+
+// Shadertoy's iMouse, in pixels from the bottom left: xy is the position while
+// dragging, zw where the click happened. z is negative once the button is
+// released and w only positive on the frame of the click.
+fn mouse_click() -> vec4<f32> {
+    return _std_uniform.mouse_click;
+}
+
+// accumulated wheel scroll, in lines
+fn scroll() -> vec2<f32> {
+    return _std_uniform.scroll;
+}
+
+fn key_down(code: u32) -> bool {
+    return textureLoad(_keyboard, vec2<u32>(code, 0u), 0).x > 0.5;
+}
+
+fn key_pressed(code: u32) -> bool {
+    return textureLoad(_keyboard, vec2<u32>(code, 1u), 0).x > 0.5;
+}
+
+fn key_toggled(code: u32) -> bool {
+    return textureLoad(_keyboard, vec2<u32>(code, 2u), 0).x > 0.5;
+}
+
 fn hex_to_rgba(a: u32) -> vec4<f32> {
-    return vec4<f32>(vec4<u32>(
-        extractBits(a, 24u, 8u),
-        extractBits(a, 16u, 8u),
-        extractBits(a, 8u, 8u), 
-        extractBits(a, 0u, 8u))) / 255.0;
+    return vec4<f32>((vec4<u32>(a) >> vec4<u32>(24u, 16u, 8u, 0u)) & vec4<u32>(0xffu)) / 255.0;
 }
 
 fn flipy(k: vec2<f32>) -> vec2<f32> {
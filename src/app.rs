@@ -1,5 +1,5 @@
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     time::{Duration, Instant},
 };
 
@@ -12,16 +12,31 @@ use notify_debouncer_full::{
 use wgpu::BindGroupLayout;
 
 use winit::{
-    event::{ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
+    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop, EventLoopProxy},
     window::{Window, WindowBuilder, WindowId},
 };
 
 use super::{
-    shader::{ShaderFileBuf, ShaderFileBuilder, Uniform, UniformKind, GUICONTROLLED_DEF},
-    texture::Texture,
+    cli::Cli,
+    editor::Editor,
+    freeze,
+    history::History,
+    inputs::{
+        Audio, Clock, EventContext, FrameContext, InputProvider, InputProviders, Mouse, WindowSize,
+    },
+    isf,
+    keyboard::Keyboard,
+    parser,
+    presets::Presets,
+    shader::{Diagnostic, ShaderFileBuf, ShaderFileBuilder, Uniform, UniformKind},
+    shadertoy, shadertoy_json,
+    sweep::Sweep,
+    texture::{decode_frames, Texture, TextureOption},
+    timeline::Timeline,
     ui::{Egui, ShadeyEvent},
-    wgsl::Sized,
+    web,
+    wgsl::{Sized, SlotValue},
 };
 
 pub struct App {
@@ -35,14 +50,20 @@ pub struct App {
     std_uniform: Uniform,
     bind_groups: Vec<wgpu::BindGroup>,
     textures: Vec<Texture>,
+    // what the textures were loaded from
+    texture_options: Vec<TextureOption>,
+    // bumped with every load of the textures, frames decoded for an older one are dropped
+    texture_generation: u64,
     start_instant: Instant,
+    inputs: InputProviders,
+    keyboard_view: wgpu::TextureView,
     ui: Egui,
     file_watcher: Debouncer<ReadDirectoryChangesWatcher, notify_debouncer_full::FileIdMap>,
     old_shader_path: PathBuf,
 }
 
 impl App {
-    pub async fn new(event_loop: &EventLoop<ShadeyEvent>) -> Self {
+    pub async fn new(event_loop: &EventLoop<ShadeyEvent>, cli: &Cli) -> Self {
         let window = WindowBuilder::new()
             .with_visible(false)
             .with_title("Shadey - ur shader toy")
@@ -86,29 +107,19 @@ impl App {
 
         let default_shader_path = std::path::PathBuf::from("shader/default.wgsl");
 
-        let (default_shader_content, std_uniform, gui_uniform, textures) = {
-            let mut default_shader_builder = ShaderFileBuilder::new(&default_shader_path)
-                .expect("Default shader should be present");
-
-            let std_uniform = default_shader_builder.uniform(&device, UniformKind::StandardLib);
-            let gui_uniform = default_shader_builder.uniform(&device, UniformKind::GuiControlled);
-
-            default_shader_builder.inject_content(GUICONTROLLED_DEF);
-            for slot in &gui_uniform.runtime_struct.slots {
-                default_shader_builder.inject_content(&slot.generate_definition());
-            }
-            let textures = default_shader_builder.textures(&device, &queue);
-            for (i, texture) in textures.iter().enumerate() {
-                default_shader_builder.inject_content(&texture.generate_definition(i));
-            }
-
-            let default_shader_content = default_shader_builder.build();
+        let thread_pool =
+            futures::executor::ThreadPool::new().expect("ThreadPool to be created without problem");
 
-            (default_shader_content, std_uniform, gui_uniform, textures)
-        };
+        let default_shader_builder =
+            ShaderFileBuilder::new(&default_shader_path).expect("Default shader should be present");
+        let std_uniform = default_shader_builder.uniform(&device, UniformKind::StandardLib);
+        let texture_options = default_shader_builder.options().to_vec();
+        let mut textures = default_shader_builder.textures(&device, &queue);
+        let (default_shader_content, mut gui_uniform) =
+            compose_shader(&device, default_shader_builder);
+        spawn_frame_decoding(&thread_pool, &mut textures, 0, &event_loop.create_proxy());
 
-        let texture_bind_group_layouts =
-            create_texture_bind_groups_layouts(&device, textures.len());
+        let texture_bind_group_layouts = create_texture_bind_groups_layouts(&device, &textures);
 
         let texture_bind_group =
             create_texture_bind_groups(&device, &textures, &texture_bind_group_layouts);
@@ -118,11 +129,21 @@ impl App {
             .chain(texture_bind_group_layouts.into_iter())
             .collect::<Vec<_>>();
 
+        let (keyboard, keyboard_view) = Keyboard::new(&device);
+        let mut inputs = InputProviders::new(vec![
+            Box::new(Clock::new()),
+            Box::new(WindowSize::default()),
+            Box::new(Mouse::default()),
+            Box::new(keyboard),
+            Box::new(Audio),
+        ]);
+        inputs.bind(&std_uniform.runtime_struct, &gui_uniform.runtime_struct);
         let bind_group = create_main_bind_group(
             &device,
             &bind_group_layouts[0],
             &std_uniform.gpu_buffer_handle,
             &gui_uniform.gpu_buffer_handle,
+            &keyboard_view,
         );
 
         let bind_groups = std::iter::once(bind_group)
@@ -135,10 +156,25 @@ impl App {
         let render_pipeline =
             create_render_pipeline(&device, &bind_group_layouts, config.format, &shader_module);
 
-        let ui = Egui::new(&window, &device, config.format, event_loop, gui_uniform);
+        let presets = Presets::for_shader(&default_shader_path);
+        presets.apply_last_used(&mut gui_uniform.runtime_struct);
+        let mut ui = Egui::new(
+            &window,
+            &device,
+            config.format,
+            event_loop,
+            gui_uniform,
+            presets,
+            &default_shader_path,
+        );
+        ui.shader_compiled(default_shader_content);
+        ui.std_layout = std_uniform.runtime_struct.layout();
+        ui.expressions
+            .bind_annotations(&ui.gui_uniform.runtime_struct);
+        if let Some(clock_mode) = cli.clock_mode {
+            ui.transport.clock_mode = clock_mode;
+        }
 
-        let thread_pool =
-            futures::executor::ThreadPool::new().expect("ThreadPool to be created without problem");
         let file_watcher = {
             let mut debouncer = create_file_watcher(event_loop);
             debouncer
@@ -161,7 +197,11 @@ impl App {
             std_uniform,
             bind_groups,
             textures,
+            texture_options,
+            texture_generation: 0,
             start_instant: Instant::now(),
+            inputs,
+            keyboard_view,
             ui,
             file_watcher,
             old_shader_path: default_shader_path,
@@ -173,44 +213,51 @@ impl App {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
-            self.std_uniform
-                .runtime_struct
-                .write_to_slot::<[u32; 2]>(1, &new_size.into()); // slot 1 is window_size
         }
     }
 
     pub fn handle_input(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::CursorMoved { position, .. } => {
-                self.std_uniform
-                    .runtime_struct
-                    .write_to_slot::<[u32; 2]>(2, &(*position).into()); // slot 2 is mouse_pos
-                true
-            }
-            WindowEvent::MouseInput {
-                state: ElementState::Pressed,
-                button: MouseButton::Left,
-                ..
-            } => {
-                let mouse_pos = self
-                    .std_uniform
-                    .runtime_struct
-                    .read_from_slot_ref_mut::<[u32; 2]>(2) // slot 2 is mouse_pos
-                    .to_owned();
-                self.std_uniform.runtime_struct.write_to_slot(4, &mouse_pos); // slot 4 is toggle_mouse_pos
-                true
-            }
-            _ => false,
-        }
+        let ctx = EventContext {
+            window_size: [self.config.width, self.config.height],
+            egui_wants_keyboard: self.ui.platform.context().wants_keyboard_input(),
+        };
+        self.inputs.handle_event(event, &ctx)
     }
 
-    pub fn update(&mut self) {
-        let time_elapsed = self.start_instant.elapsed().as_secs_f64();
-        self.ui.platform.update_time(time_elapsed);
+    /// Entry point for custom inputs, e.g. sensor data replayed from a file
+    pub fn register_input(&mut self, provider: Box<dyn InputProvider>) {
+        self.inputs.push(provider);
+        self.inputs.bind(
+            &self.std_uniform.runtime_struct,
+            &self.ui.gui_uniform.runtime_struct,
+        );
+    }
 
-        self.std_uniform.runtime_struct.write_to_slot(
-            3, // slot 3 is time
-            &(time_elapsed as f32),
+    pub fn update(&mut self) {
+        self.ui
+            .platform
+            .update_time(self.start_instant.elapsed().as_secs_f64());
+
+        self.inputs.tick(&mut FrameContext {
+            queue: &self.queue,
+            textures: &mut self.textures,
+            transport: &mut self.ui.transport,
+            window_size: [self.config.width, self.config.height],
+            time: 0.0,
+            delta_time: 0.0,
+        });
+        self.inputs.write(
+            &mut self.std_uniform.runtime_struct,
+            &mut self.ui.gui_uniform.runtime_struct,
+        );
+        self.inputs.end_frame();
+        self.ui.timeline.apply(
+            self.ui.transport.time,
+            &mut self.ui.gui_uniform.runtime_struct,
+        );
+        self.ui.expressions.apply(
+            &self.std_uniform.runtime_struct,
+            &mut self.ui.gui_uniform.runtime_struct,
         );
 
         self.queue.write_buffer(
@@ -285,7 +332,8 @@ impl App {
         match event {
             ShadeyEvent::OpenFileDialog => {
                 let dialog = rfd::AsyncFileDialog::new()
-                    .add_filter("shader", &["wgsl"])
+                    .add_filter("shader", &["wgsl", "glsl", "frag", "fs"])
+                    .add_filter("Shadertoy export", &["json"])
                     .set_parent(&self.window)
                     .set_directory(std::env::current_dir().expect("We should have a workdir"))
                     .pick_file();
@@ -293,98 +341,401 @@ impl App {
                 let event_loop_proxy_clone = event_loop_proxy.clone();
                 self.thread_pool.spawn_ok(async move {
                     let new_shader_file = dialog.await.unwrap();
+                    let path = new_shader_file.path().to_owned();
+                    let event = match path.extension() {
+                        Some(extension) if extension == "json" => {
+                            ShadeyEvent::ImportShadertoy(path)
+                        }
+                        _ => ShadeyEvent::ReloadShader(path),
+                    };
                     event_loop_proxy_clone
-                        .send_event(ShadeyEvent::ReloadShader(new_shader_file.path().to_owned()))
+                        .send_event(event)
                         .expect("Event loop should send event...");
                 });
             }
+            ShadeyEvent::ImportShadertoy(export_path) => {
+                let import = shadertoy_json::import(&export_path);
+                match &import {
+                    Ok(import) => import.report(),
+                    Err(e) => eprintln!("{e}"),
+                }
+                let image = import.as_ref().ok().map(|import| import.image.clone());
+                self.ui.import_report = Some(import);
+                if let Some(image) = image {
+                    self.handle_user_event(ShadeyEvent::ReloadShader(image), event_loop_proxy);
+                }
+            }
             ShadeyEvent::ReloadShader(new_shader_path) => {
-                let (shader_content, candidate_uniform, textures) = {
-                    let Some(mut shader_builder) = ShaderFileBuilder::new(&new_shader_path) else {
-                        eprintln!("Shader wasn't found");
+                if new_shader_path != self.old_shader_path && self.ui.editor.is_dirty() {
+                    let message = format!(
+                        "{:?} has unsaved edits, save or reload it before opening another shader",
+                        self.ui.editor.path()
+                    );
+                    eprintln!("{message}");
+                    self.ui.editor.visible = true;
+                    self.ui.editor.diagnostic = Some(Diagnostic {
+                        message,
+                        line: None,
+                    });
+                    return;
+                }
+                if new_shader_path == self.old_shader_path {
+                    self.ui.editor.disk_changed();
+                }
+                let Ok(source) = std::fs::read_to_string(&new_shader_path) else {
+                    eprintln!("Shader wasn't found");
+                    return;
+                };
+                let shader_builder = match shader_builder(&new_shader_path, &source) {
+                    Ok(shader_builder) => shader_builder,
+                    Err(diagnostic) => {
+                        eprintln!("{}", diagnostic.message);
+                        self.ui.editor.diagnostic = Some(diagnostic);
                         return;
-                    };
+                    }
+                };
+                if !self.load_shader(shader_builder, event_loop_proxy, false) {
+                    return;
+                }
 
-                    let candidate_uniform =
-                        shader_builder.uniform(&self.device, UniformKind::GuiControlled);
+                if new_shader_path != self.old_shader_path {
+                    self.ui.presets = Presets::for_shader(&new_shader_path);
+                    self.ui
+                        .presets
+                        .apply_last_used(&mut self.ui.gui_uniform.runtime_struct);
+                    self.ui.timeline = Timeline::for_shader(&new_shader_path);
+                    self.ui.history = History::default();
+                    let editor_visible = self.ui.editor.visible;
+                    self.ui.editor = Editor::open(&new_shader_path);
+                    self.ui.editor.visible = editor_visible;
 
-                    shader_builder.inject_content(GUICONTROLLED_DEF);
-                    for slot in &candidate_uniform.runtime_struct.slots {
-                        shader_builder.inject_content(&slot.generate_definition());
-                    }
+                    self.file_watcher
+                        .watcher()
+                        .unwatch(&self.old_shader_path)
+                        .expect("Old shader path should be exist (was already used)");
+                    self.file_watcher
+                        .watcher()
+                        .watch(&new_shader_path, RecursiveMode::NonRecursive)
+                        .expect("Shader path should exist (was already validated)");
 
-                    let textures = shader_builder.textures(&self.device, &self.queue);
-                    for (i, texture) in textures.iter().enumerate() {
-                        shader_builder.inject_content(&texture.generate_definition(i));
+                    self.old_shader_path = new_shader_path;
+                }
+            }
+            ShadeyEvent::CompileSource(source) => {
+                match shader_builder(self.ui.editor.path(), &source) {
+                    Ok(shader_builder) => {
+                        self.load_shader(shader_builder, event_loop_proxy, true);
                     }
-                    let shader_content = shader_builder.build();
-
-                    (shader_content, candidate_uniform, textures)
+                    Err(diagnostic) => self.ui.editor.diagnostic = Some(diagnostic),
+                }
+            }
+            ShadeyEvent::RenderContactSheet(sweep) => self.render_contact_sheet(&sweep),
+            ShadeyEvent::ExportWebPage { controls } => self.export_web_page(controls),
+            ShadeyEvent::ExportFrozen => self.export_frozen(),
+            ShadeyEvent::FramesDecoded {
+                generation,
+                name,
+                frames,
+            } => {
+                if generation != self.texture_generation {
+                    return;
+                }
+                let Some(texture) = self
+                    .textures
+                    .iter_mut()
+                    .find(|t| t.name == name && t.animation.is_some())
+                else {
+                    return;
                 };
+                texture.replace_frames(&self.device, &self.queue, &frames);
 
+                // identical layouts are deduplicated by wgpu, the pipeline stays compatible
                 let texture_bind_group_layouts =
-                    create_texture_bind_groups_layouts(&self.device, textures.len());
-                let texture_bind_group = create_texture_bind_groups(
+                    create_texture_bind_groups_layouts(&self.device, &self.textures);
+                let texture_bind_groups = create_texture_bind_groups(
                     &self.device,
-                    &textures,
+                    &self.textures,
                     &texture_bind_group_layouts,
                 );
-                let maybe_shader_module = create_shader_module(&self.device, &shader_content);
+                self.bind_groups.truncate(1);
+                self.bind_groups.extend(texture_bind_groups);
+            }
+        }
+    }
 
-                if let Err(e) = maybe_shader_module {
-                    eprintln!("{e}");
-                    return;
-                }
+    /// Compile and swap in a new shader, the current one is kept on errors.
+    /// Compiles `from_editor` happen at every pause in typing, they keep the
+    /// textures while their options are the same and don't reset the transport.
+    fn load_shader(
+        &mut self,
+        shader_builder: ShaderFileBuilder,
+        event_loop_proxy: &EventLoopProxy<ShadeyEvent>,
+        from_editor: bool,
+    ) -> bool {
+        let texture_options = shader_builder.options().to_vec();
+        let new_textures = (!from_editor || texture_options != self.texture_options)
+            .then(|| shader_builder.textures(&self.device, &self.queue));
+        let (shader_content, candidate_uniform) = compose_shader(&self.device, shader_builder);
+        let textures = new_textures.as_ref().unwrap_or(&self.textures);
+
+        if let Err(diagnostic) = shader_content.check() {
+            eprintln!("{}", diagnostic.message);
+            self.ui.editor.diagnostic = Some(diagnostic);
+            return false;
+        }
+        let texture_bind_group_layouts = create_texture_bind_groups_layouts(&self.device, textures);
+        let texture_bind_group =
+            create_texture_bind_groups(&self.device, textures, &texture_bind_group_layouts);
+        let shader_module = match create_shader_module(&self.device, &shader_content) {
+            Ok(shader_module) => shader_module,
+            Err(e) => {
+                eprintln!("{e}");
+                self.ui.editor.diagnostic = Some(Diagnostic {
+                    message: e.to_owned(),
+                    line: None,
+                });
+                return false;
+            }
+        };
+        self.ui.editor.diagnostic = None;
+        self.ui.shader_compiled(shader_content);
 
-                let shader_module = maybe_shader_module.unwrap();
+        if self.ui.transport.reset_on_reload && !from_editor {
+            self.ui.transport.reset();
+        }
+        if let Some(mut textures) = new_textures {
+            self.texture_generation += 1;
+            spawn_frame_decoding(
+                &self.thread_pool,
+                &mut textures,
+                self.texture_generation,
+                event_loop_proxy,
+            );
+            self.textures = textures;
+            self.texture_options = texture_options;
+        }
 
-                self.textures = textures;
+        if candidate_uniform.runtime_struct.slots != self.ui.gui_uniform.runtime_struct.slots {
+            self.ui.gui_uniform = candidate_uniform;
+            self.ui
+                .expressions
+                .bind_annotations(&self.ui.gui_uniform.runtime_struct);
+            self.inputs.bind(
+                &self.std_uniform.runtime_struct,
+                &self.ui.gui_uniform.runtime_struct,
+            );
+        }
+        let bind_group_layout =
+            create_main_bind_group_layout(&self.device, &self.std_uniform, &self.ui.gui_uniform);
 
-                if candidate_uniform.runtime_struct.slots
-                    != self.ui.gui_uniform.runtime_struct.slots
-                {
-                    self.ui.gui_uniform = candidate_uniform;
-                }
-                let bind_group_layout = create_main_bind_group_layout(
-                    &self.device,
-                    &self.std_uniform,
-                    &self.ui.gui_uniform,
-                );
+        let bind_group_layouts: Vec<BindGroupLayout> = std::iter::once(bind_group_layout)
+            .chain(texture_bind_group_layouts.into_iter())
+            .collect();
+
+        let bind_group = create_main_bind_group(
+            &self.device,
+            &bind_group_layouts[0],
+            &self.std_uniform.gpu_buffer_handle,
+            &self.ui.gui_uniform.gpu_buffer_handle,
+            &self.keyboard_view,
+        );
 
-                let bind_group_layouts: Vec<BindGroupLayout> = std::iter::once(bind_group_layout)
-                    .chain(texture_bind_group_layouts.into_iter())
-                    .collect();
+        self.bind_groups = std::iter::once(bind_group)
+            .chain(texture_bind_group.into_iter())
+            .collect();
+        self.render_pipeline = create_render_pipeline(
+            &self.device,
+            &bind_group_layouts,
+            self.config.format,
+            &shader_module,
+        );
+        true
+    }
 
-                let bind_group = create_main_bind_group(
-                    &self.device,
-                    &bind_group_layouts[0],
-                    &self.std_uniform.gpu_buffer_handle,
-                    &self.ui.gui_uniform.gpu_buffer_handle,
-                );
+    /// Writes the last compiled shader as an html page next to it
+    fn export_web_page(&self, controls: bool) {
+        let Some(source) = self.ui.generated() else {
+            eprintln!("No shader compiled yet, nothing to export");
+            return;
+        };
+        let (_, options) = parser::parse_options(source.as_ref()).unwrap_or(("", Vec::new()));
+        let title = self
+            .old_shader_path
+            .file_stem()
+            .map_or("shadey".into(), |stem| stem.to_string_lossy());
+        let (page, notes) = web::page(
+            &title,
+            source,
+            &self.ui.std_layout,
+            &self.ui.gui_uniform.runtime_struct,
+            &self.textures,
+            &options,
+            controls,
+        );
 
-                self.bind_groups = std::iter::once(bind_group)
-                    .chain(texture_bind_group.into_iter())
-                    .collect();
-                self.render_pipeline = create_render_pipeline(
-                    &self.device,
-                    &bind_group_layouts,
-                    self.config.format,
-                    &shader_module,
-                );
+        let path = web::export_path(&self.old_shader_path);
+        if let Err(e) = std::fs::write(&path, page) {
+            eprintln!("Web page couldn't be written to {path:?}: {e}");
+            return;
+        }
+        eprintln!("Web page exported to {path:?}");
+        for note in notes {
+            eprintln!("  {note}");
+        }
+        // the page plays the values as they are now, not the keyframes
+        for name in self.ui.timeline.tracks() {
+            eprintln!("  {name}: the timeline keyframes aren't exported, its current value is");
+        }
+    }
 
-                if new_shader_path != self.old_shader_path {
-                    self.file_watcher
-                        .watcher()
-                        .unwatch(&self.old_shader_path)
-                        .expect("Old shader path should be exist (was already used)");
-                    self.file_watcher
-                        .watcher()
-                        .watch(&new_shader_path, RecursiveMode::NonRecursive)
-                        .expect("Shader path should exist (was already validated)");
+    fn export_frozen(&self) {
+        let Some(source) = self.ui.generated() else {
+            eprintln!("No shader compiled yet, nothing to export");
+            return;
+        };
+        let origin = self
+            .old_shader_path
+            .file_name()
+            .map_or("shadey".into(), |name| name.to_string_lossy());
+        let frozen = match freeze::freeze(source, &self.ui.gui_uniform.runtime_struct, &origin) {
+            Ok(frozen) => frozen,
+            Err(e) => {
+                eprintln!("Shader couldn't be frozen: {e}");
+                return;
+            }
+        };
 
-                    self.old_shader_path = new_shader_path;
+        let path = freeze::export_path(&self.old_shader_path);
+        match std::fs::write(&path, frozen) {
+            Ok(_) => eprintln!("Frozen shader exported to {path:?}"),
+            Err(e) => eprintln!("Frozen shader couldn't be written to {path:?}: {e}"),
+        }
+    }
+
+    /// Renders every cell of the sweep offscreen, then saves them as one png next to the shader
+    fn render_contact_sheet(&mut self, sweep: &Sweep) {
+        let [width, height] = sweep.thumbnail_size;
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let padded_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let cell_size = (padded_row * height) as wgpu::BufferAddress;
+        let cells = sweep.cells();
+
+        let thumbnail = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("sweep_thumbnail"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = thumbnail.create_view(&wgpu::TextureViewDescriptor::default());
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sweep_readback"),
+            // one cell at a time, the whole sheet can outgrow max_buffer_size
+            size: cell_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        // the shader sees the thumbnail as its window, the next frame writes the real size back
+        let std_struct = &mut self.std_uniform.runtime_struct;
+        if let Some(slot) = std_struct.get_slot_number("window_size") {
+            std_struct.write_to_slot(slot, &[width, height]);
+        }
+        self.queue.write_buffer(
+            &self.std_uniform.gpu_buffer_handle,
+            0,
+            self.std_uniform.runtime_struct.buffer(),
+        );
+
+        let bgra = matches!(
+            self.config.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        let gui_struct = &mut self.ui.gui_uniform.runtime_struct;
+        let original_values = gui_struct.values();
+        let mut thumbnails = Vec::with_capacity(cells.len());
+        for cell in &cells {
+            for (name, value) in cell {
+                if let Some(slot) = gui_struct.get_slot_number(name) {
+                    gui_struct.set_slot_value(slot, &SlotValue::Scalar(*value as f64));
                 }
             }
+            self.queue.write_buffer(
+                &self.ui.gui_uniform.gpu_buffer_handle,
+                0,
+                gui_struct.buffer(),
+            );
+
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Sweep Encoder"),
+                });
+            {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Sweep Render Pass"),
+                    color_attachments: &[wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: true,
+                        },
+                    }
+                    .into()],
+                    depth_stencil_attachment: None,
+                });
+                render_pass.set_pipeline(&self.render_pipeline);
+                for (i, group) in self.bind_groups.iter().enumerate() {
+                    render_pass.set_bind_group(i as _, group, &[]);
+                }
+                render_pass.draw(0..3, 0..1);
+            }
+            encoder.copy_texture_to_buffer(
+                thumbnail.as_image_copy(),
+                wgpu::ImageCopyBuffer {
+                    buffer: &readback,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(padded_row),
+                        rows_per_image: Some(height),
+                    },
+                },
+                size,
+            );
+            // uniform writes land before the submit that follows them
+            self.queue.submit(std::iter::once(encoder.finish()));
+
+            let slice = readback.slice(..);
+            slice.map_async(wgpu::MapMode::Read, |_| {});
+            self.device.poll(wgpu::Maintain::Wait);
+            let pixels = slice.get_mapped_range();
+            thumbnails.push(image::RgbaImage::from_fn(width, height, |x, y| {
+                let i = (y * padded_row + x * 4) as usize;
+                let [r, g, b] = if bgra {
+                    [pixels[i + 2], pixels[i + 1], pixels[i]]
+                } else {
+                    [pixels[i], pixels[i + 1], pixels[i + 2]]
+                };
+                image::Rgba([r, g, b, 255])
+            }));
+            drop(pixels);
+            readback.unmap();
+        }
+        gui_struct.apply_values(&original_values);
+
+        let path = sweep.output_path(&self.old_shader_path);
+        let sheet = sweep.compose(&sweep.title(&self.old_shader_path), &thumbnails);
+        match sheet.save(&path) {
+            Ok(_) => eprintln!("Contact sheet saved to {path:?}"),
+            Err(e) => eprintln!("Contact sheet couldn't be saved to {path:?}: {e}"),
         }
     }
 
@@ -426,6 +777,67 @@ impl App {
     }
 }
 
+/// Shadertoy GLSL is translated to WGSL before being composed
+pub fn shader_builder(path: &Path, source: &str) -> Result<ShaderFileBuilder, Diagnostic> {
+    let translated;
+    let source = if shadertoy::is_shadertoy(path) {
+        translated = shadertoy::to_wgsl(source)?;
+        &translated
+    } else if isf::is_isf(path) {
+        let notes;
+        (translated, notes) = isf::to_wgsl(source, path)?;
+        for note in notes {
+            eprintln!("{note}");
+        }
+        &translated
+    } else {
+        source
+    };
+    ShaderFileBuilder::from_source(source).ok_or_else(|| Diagnostic {
+        message: "GuiControlled struct wasn't found".to_owned(),
+        line: None,
+    })
+}
+
+/// Compose the source and create its `GuiControlled` buffer, the textures
+/// are loaded apart so they can outlive a recompile
+fn compose_shader(
+    device: &wgpu::Device,
+    shader_builder: ShaderFileBuilder,
+) -> (ShaderFileBuf, Uniform) {
+    let (shader_content, gui_struct) = shader_builder.compose();
+    let gui_uniform = Uniform::new(device, gui_struct, UniformKind::GuiControlled);
+
+    (shader_content, gui_uniform)
+}
+
+/// `--emit-wgsl`: print the composed source of a shader, and the layout of
+/// its uniforms to stderr. Nothing is loaded, no GPU is needed.
+pub fn emit_wgsl(shader_path: &Path) -> Result<(), String> {
+    let source = std::fs::read_to_string(shader_path)
+        .map_err(|e| format!("{shader_path:?} couldn't be read: {e}"))?;
+    let shader_builder = shader_builder(shader_path, &source).map_err(|d| d.message)?;
+    let std_struct = shader_builder.runtime_struct(UniformKind::StandardLib);
+    let (shader_content, gui_struct) = shader_builder.compose();
+
+    println!("{}", shader_content.as_ref());
+    for (name, runtime_struct) in [("StdUniform", &std_struct), ("GuiControlled", &gui_struct)] {
+        let layout = runtime_struct.layout();
+        eprintln!("{name}: size {}, align {}", layout.size, layout.align);
+        eprintln!(
+            "  {:<16} {:<16} {:>6} {:>6} {:>6}",
+            "field", "type", "offset", "size", "align"
+        );
+        for field in layout.fields {
+            eprintln!(
+                "  {:<16} {:<16} {:>6} {:>6} {:>6}",
+                field.identifier, field.typed, field.offset, field.size, field.align
+            );
+        }
+    }
+    Ok(())
+}
+
 fn create_shader_module(
     device: &wgpu::Device,
     shader_content: &ShaderFileBuf,
@@ -540,6 +952,16 @@ fn create_main_bind_group_layout(
                 },
                 count: None,
             },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
         ],
         label: Some("main_bind_group_layout"),
     })
@@ -550,6 +972,7 @@ fn create_main_bind_group(
     bind_group_layout: &wgpu::BindGroupLayout,
     std_gpu_buffer_handle: &wgpu::Buffer,
     gui_gpu_buffer_handle: &wgpu::Buffer,
+    keyboard_view: &wgpu::TextureView,
 ) -> wgpu::BindGroup {
     device.create_bind_group(&wgpu::BindGroupDescriptor {
         layout: bind_group_layout,
@@ -562,6 +985,10 @@ fn create_main_bind_group(
                 binding: 1,
                 resource: gui_gpu_buffer_handle.as_entire_binding(),
             },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(keyboard_view),
+            },
         ],
         label: Some("main_bind_group"),
     })
@@ -594,9 +1021,9 @@ fn create_texture_bind_groups(
 
 fn create_texture_bind_groups_layouts(
     device: &wgpu::Device,
-    nb_required: usize,
+    textures: &[Texture],
 ) -> Vec<wgpu::BindGroupLayout> {
-    (0..nb_required)
+    (0..textures.len())
         .map(|i| {
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
@@ -605,7 +1032,7 @@ fn create_texture_bind_groups_layouts(
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Texture {
                             multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
+                            view_dimension: textures[i].view_dimension,
                             sample_type: wgpu::TextureSampleType::Float { filterable: true },
                         },
                         count: None,
@@ -623,6 +1050,38 @@ fn create_texture_bind_groups_layouts(
         .collect()
 }
 
+fn spawn_frame_decoding(
+    thread_pool: &ThreadPool,
+    textures: &mut [Texture],
+    generation: u64,
+    event_loop_proxy: &EventLoopProxy<ShadeyEvent>,
+) {
+    for texture in textures {
+        let Some(source) = texture
+            .animation
+            .as_mut()
+            .and_then(|a| a.pending_frames.take())
+        else {
+            continue;
+        };
+
+        let name = texture.name.clone();
+        let event_loop_proxy = event_loop_proxy.clone();
+        thread_pool.spawn_ok(async move {
+            match decode_frames(&source) {
+                Some(frames) => event_loop_proxy
+                    .send_event(ShadeyEvent::FramesDecoded {
+                        generation,
+                        name,
+                        frames,
+                    })
+                    .expect("Event loop should send event..."),
+                None => eprintln!("Frames of {source:?} couldn't be decoded"),
+            }
+        });
+    }
+}
+
 fn create_file_watcher(
     event_loop: &EventLoop<ShadeyEvent>,
 ) -> Debouncer<ReadDirectoryChangesWatcher, notify_debouncer_full::FileIdMap> {
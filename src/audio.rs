@@ -0,0 +1,155 @@
+use std::{f32::consts::PI, path::Path};
+
+/// Same layout as Shadertoy's sound input: 512 texels of spectrum then 512 of waveform
+pub const AUDIO_TEXTURE_WIDTH: u32 = 512;
+const FFT_SIZE: usize = 2 * AUDIO_TEXTURE_WIDTH as usize;
+
+// WebAudio AnalyserNode defaults, which Shadertoy uses
+const SMOOTHING: f32 = 0.8;
+const MIN_DB: f32 = -100.0;
+const MAX_DB: f32 = -30.0;
+
+/// A decoded track analysed on the cpu, so no audio device is needed
+pub struct AudioTrack {
+    samples: Vec<f32>,
+    sample_rate: u32,
+    smoothed_spectrum: Vec<f32>,
+}
+
+impl AudioTrack {
+    pub fn from_wav(path: &Path) -> Option<Self> {
+        let reader = hound::WavReader::open(path).ok()?;
+        let spec = reader.spec();
+        let interleaved = match spec.sample_format {
+            hound::SampleFormat::Float => reader
+                .into_samples::<f32>()
+                .collect::<Result<Vec<_>, _>>()
+                .ok()?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u32 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .into_samples::<i32>()
+                    .map(|s| s.map(|s| s as f32 * scale))
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok()?
+            }
+        };
+
+        let channels = spec.channels as usize;
+        let samples = interleaved
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect::<Vec<_>>();
+        if samples.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            samples,
+            sample_rate: spec.sample_rate,
+            smoothed_spectrum: vec![0.0; AUDIO_TEXTURE_WIDTH as usize],
+        })
+    }
+
+    /// Texels of the 512x2 audio texture for the window ending at `time`,
+    /// the track loops when time goes past its end.
+    pub fn analyse(&mut self, time: f32) -> Vec<u8> {
+        let end = (time.max(0.0) * self.sample_rate as f32) as usize;
+        let window = (0..FFT_SIZE)
+            .map(|i| {
+                let index = (end + i) as isize - FFT_SIZE as isize;
+                self.samples[index.rem_euclid(self.samples.len() as isize) as usize]
+            })
+            .collect::<Vec<_>>();
+
+        let mut re = window
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s * blackman(i, FFT_SIZE))
+            .collect::<Vec<_>>();
+        let mut im = vec![0.0; FFT_SIZE];
+        fft(&mut re, &mut im);
+
+        for (bin, smoothed) in self.smoothed_spectrum.iter_mut().enumerate() {
+            let magnitude = (re[bin] * re[bin] + im[bin] * im[bin]).sqrt() / FFT_SIZE as f32;
+            *smoothed = SMOOTHING * *smoothed + (1.0 - SMOOTHING) * magnitude;
+        }
+
+        let spectrum = self.smoothed_spectrum.iter().map(|m| {
+            let db = 20.0 * m.max(1e-10).log10();
+            ((db - MIN_DB) / (MAX_DB - MIN_DB)).clamp(0.0, 1.0)
+        });
+        let waveform = window[FFT_SIZE - AUDIO_TEXTURE_WIDTH as usize..]
+            .iter()
+            .map(|s| (0.5 + 0.5 * s).clamp(0.0, 1.0));
+
+        spectrum
+            .chain(waveform)
+            .map(|v| (v * 255.0).round() as u8)
+            .collect()
+    }
+}
+
+fn blackman(i: usize, n: usize) -> f32 {
+    let x = 2.0 * PI * i as f32 / n as f32;
+    0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos()
+}
+
+/// In place iterative radix-2 fft, the length must be a power of two
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if j > i {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len *= 2;
+    }
+}
+
+#[allow(unused)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::audio::fft;
+
+    #[test]
+    fn fft_finds_pure_tone() {
+        let n = 64;
+        let mut re = (0..n)
+            .map(|i| (2.0 * PI * 5.0 * i as f32 / n as f32).sin())
+            .collect::<Vec<_>>();
+        let mut im = vec![0.0; n];
+        fft(&mut re, &mut im);
+
+        let magnitudes = (0..n / 2)
+            .map(|i| (re[i] * re[i] + im[i] * im[i]).sqrt())
+            .collect::<Vec<_>>();
+        let peak = magnitudes
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap()
+            .0;
+        assert!(peak == 5);
+    }
+}
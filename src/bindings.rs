@@ -0,0 +1,348 @@
+//! Host-side copies of a uniform struct, laid out like shadey lays out its
+//! buffer: a Rust `Pod` struct with its layout test, a C header and a
+//! TypeScript `DataView` writer, padding spelled out in each.
+
+use std::path::{Path, PathBuf};
+
+use super::{
+    app, parser,
+    wgsl::{PType, RuntimeStruct, Sized, StructSlot, TType},
+};
+
+/// A member of the host struct, padding when it has no type
+struct Member<'a> {
+    name: String,
+    typed: Option<&'a TType>,
+    offset: usize,
+    size: usize,
+}
+
+pub struct Bindings {
+    pub rust: String,
+    pub c: String,
+    pub typescript: String,
+}
+
+/// `GuiControlled` -> `gui_controlled`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && previous_lower {
+            snake.push('_');
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// The scalar type and the array dimensions, outermost first.
+/// Matrices are column arrays, packed like shadey packs them.
+fn dimensions(typed: &TType) -> (PType, Vec<usize>) {
+    match typed {
+        TType::Scalar(x) => (*x, vec![]),
+        TType::Vector(n, x) => (*x, vec![*n]),
+        TType::Matrix { m, n, typed: x } => (*x, vec![*m, *n]),
+        TType::Array(n, x) => {
+            let (x, mut dims) = dimensions(x);
+            dims.insert(0, *n);
+            (x, dims)
+        }
+    }
+}
+
+/// The scalar's name in Rust, C and a `DataView` setter, None for the
+/// types a host can't share a buffer of
+fn host_scalar(ptype: PType) -> Option<(&'static str, &'static str, &'static str)> {
+    match ptype {
+        PType::F16 => Some(("half::f16", "uint16_t", "setFloat16")),
+        PType::F32 => Some(("f32", "float", "setFloat32")),
+        PType::I32 => Some(("i32", "int32_t", "setInt32")),
+        PType::U32 => Some(("u32", "uint32_t", "setUint32")),
+        PType::Bool | PType::I64 | PType::U64 | PType::F64 => None,
+    }
+}
+
+fn rust_scalar(ptype: PType) -> &'static str {
+    host_scalar(ptype).map_or("", |(rust, _, _)| rust)
+}
+
+fn c_scalar(ptype: PType) -> &'static str {
+    host_scalar(ptype).map_or("", |(_, c, _)| c)
+}
+
+fn data_view_setter(ptype: PType) -> &'static str {
+    host_scalar(ptype).map_or("", |(_, _, setter)| setter)
+}
+
+/// The slots in order, with padding members wherever shadey leaves a gap
+fn members(runtime_struct: &RuntimeStruct) -> (Vec<Member<'_>>, usize) {
+    let layout = runtime_struct.layout();
+    let mut members = Vec::new();
+    let mut end = 0;
+    let mut pads = 0;
+    let mut pad = |members: &mut Vec<Member>, from: usize, to: usize| {
+        if to > from {
+            members.push(Member {
+                name: format!("_pad{pads}"),
+                typed: None,
+                offset: from,
+                size: to - from,
+            });
+            pads += 1;
+        }
+    };
+
+    for (slot, field) in runtime_struct.slots.iter().zip(layout.fields) {
+        pad(&mut members, end, field.offset);
+        members.push(Member {
+            name: slot.identifier.clone(),
+            typed: Some(&slot.typed),
+            offset: field.offset,
+            size: field.size,
+        });
+        end = field.offset + field.size;
+    }
+    pad(&mut members, end, layout.size);
+    (members, layout.size)
+}
+
+fn rust(name: &str, members: &[Member], size: usize, header: &str) -> String {
+    let mut fields = String::new();
+    let mut asserts = String::new();
+    for member in members {
+        let typed = match member.typed {
+            Some(typed) => {
+                let (ptype, dims) = dimensions(typed);
+                let offset = member.offset;
+                asserts += &format!(
+                    "        assert_eq!(std::mem::offset_of!({name}, {}), {offset});\n",
+                    member.name
+                );
+                dims.iter()
+                    .rev()
+                    .fold(rust_scalar(ptype).to_owned(), |inner, n| {
+                        format!("[{inner}; {n}]")
+                    })
+            }
+            None => format!("[u8; {}]", member.size),
+        };
+        fields += &format!("    pub {}: {typed},\n", member.name);
+    }
+
+    format!(
+        "{header}\
+         #[repr(C)]\n\
+         #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]\n\
+         pub struct {name} {{\n{fields}}}\n\
+         \n\
+         #[cfg(test)]\n\
+         mod tests {{\n\
+         \x20   use super::{name};\n\
+         \n\
+         \x20   #[test]\n\
+         \x20   fn {snake}_layout() {{\n\
+         \x20       assert_eq!(std::mem::size_of::<{name}>(), {size});\n\
+         {asserts}\
+         \x20   }}\n\
+         }}\n",
+        snake = snake_case(name),
+    )
+}
+
+fn c(name: &str, members: &[Member], size: usize, header: &str) -> String {
+    let guard = snake_case(name).to_uppercase() + "_H";
+    let mut fields = String::new();
+    let mut asserts = format!("_Static_assert(sizeof({name}) == {size}, \"{name} size\");\n");
+    for member in members {
+        let (scalar, dims) = match member.typed {
+            Some(typed) => {
+                let (ptype, dims) = dimensions(typed);
+                asserts += &format!(
+                    "_Static_assert(offsetof({name}, {field}) == {offset}, \"{name}.{field} offset\");\n",
+                    field = member.name,
+                    offset = member.offset
+                );
+                (c_scalar(ptype), dims)
+            }
+            None => ("uint8_t", vec![member.size]),
+        };
+        let dims: String = dims.iter().map(|n| format!("[{n}]")).collect();
+        fields += &format!("    {scalar} {}{dims};\n", member.name);
+    }
+
+    format!(
+        "{header}\
+         #ifndef {guard}\n\
+         #define {guard}\n\
+         \n\
+         #include <stddef.h>\n\
+         #include <stdint.h>\n\
+         \n\
+         typedef struct {name} {{\n{fields}}} {name};\n\
+         \n\
+         {asserts}\
+         \n\
+         #endif\n"
+    )
+}
+
+fn typescript(name: &str, members: &[Member], size: usize, header: &str) -> String {
+    let mut fields = String::new();
+    let mut writes = String::new();
+    for member in members {
+        let Some(typed) = member.typed else {
+            continue;
+        };
+        let (ptype, dims) = dimensions(typed);
+        fields += &format!("    {}: number{};\n", member.name, "[]".repeat(dims.len()));
+
+        let count: usize = dims.iter().product();
+        for k in 0..count {
+            // row-major over the dimensions, the innermost one is contiguous
+            let mut index = String::new();
+            let mut stride = count;
+            for n in &dims {
+                stride /= n;
+                index += &format!("[{}]", k / stride % n);
+            }
+            writes += &format!(
+                "    view.{}(offset + {}, value.{}{index}, true);\n",
+                data_view_setter(ptype),
+                member.offset + k * ptype.size(),
+                member.name
+            );
+        }
+    }
+
+    format!(
+        "{header}\
+         export interface {name} {{\n{fields}}}\n\
+         \n\
+         export const {screaming}_SIZE = {size};\n\
+         \n\
+         export function write{name}(view: DataView, value: {name}, offset = 0): void {{\n\
+         {writes}\
+         }}\n",
+        screaming = snake_case(name).to_uppercase(),
+    )
+}
+
+/// Errs on the first member whose type can't be shared with the host,
+/// shadey can't lay those out either
+fn host_shareable(name: &str, slots: &[StructSlot]) -> Result<(), String> {
+    for slot in slots {
+        let (ptype, _) = dimensions(&slot.typed);
+        if host_scalar(ptype).is_none() {
+            return Err(format!(
+                "{name}.{}: {} isn't host-shareable",
+                slot.identifier,
+                String::from(&slot.typed)
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn generate(name: &str, runtime_struct: &RuntimeStruct, origin: &str) -> Bindings {
+    let (members, size) = members(runtime_struct);
+    let header = format!("// Generated by shadey from {origin}, regenerate instead of editing\n\n");
+    Bindings {
+        rust: rust(name, &members, size, &header),
+        c: c(name, &members, size, &header),
+        typescript: typescript(name, &members, size, &header),
+    }
+}
+
+/// Writes the bindings of the struct `name` next to the shader, e.g.
+/// `voronoi.gui_controlled.rs`, `.h` and `.ts`. The std lib's structs can
+/// be named too.
+pub fn export(shader_path: &Path, name: &str) -> Result<Vec<PathBuf>, String> {
+    let source = std::fs::read_to_string(shader_path)
+        .map_err(|e| format!("{shader_path:?} couldn't be read: {e}"))?;
+    let source = app::shader_builder(shader_path, &source)
+        .map_err(|d| d.message)?
+        .build();
+    let (_, slots) = parser::parse_struct_named(source.as_ref(), name)
+        .map_err(|_| format!("No struct {name} in {shader_path:?}"))?;
+    if slots.is_empty() {
+        return Err(format!("{name} has no members"));
+    }
+    host_shareable(name, &slots)?;
+
+    let origin = shader_path
+        .file_name()
+        .map_or("a shader".into(), |name| name.to_string_lossy());
+    let bindings = generate(name, &RuntimeStruct::new(slots), &origin);
+    let snake = snake_case(name);
+    [
+        ("rs", bindings.rust),
+        ("h", bindings.c),
+        ("ts", bindings.typescript),
+    ]
+    .into_iter()
+    .map(|(extension, text)| {
+        let path = shader_path.with_extension(format!("{snake}.{extension}"));
+        std::fs::write(&path, text)
+            .map(|_| path.clone())
+            .map_err(|e| format!("{path:?} couldn't be written: {e}"))
+    })
+    .collect()
+}
+
+#[allow(unused)]
+mod tests {
+    use crate::{
+        bindings::{generate, host_shareable, snake_case},
+        parser::parse_struct_named,
+        wgsl::RuntimeStruct,
+    };
+
+    #[test]
+    fn names() {
+        assert!(snake_case("GuiControlled") == "gui_controlled");
+        assert!(snake_case("StdUniform") == "std_uniform");
+    }
+
+    #[test]
+    fn padding_is_explicit() {
+        let source = "struct GuiControlled {\r\n    speed: f32,\r\n    tint: vec3<f32>,\r\n    center: vec2<f32>,\r\n};\r\n";
+        let (_, slots) = parse_struct_named(source, "GuiControlled").unwrap();
+        let bindings = generate("GuiControlled", &RuntimeStruct::new(slots), "rings.wgsl");
+
+        assert!(bindings
+            .rust
+            .contains("    pub speed: f32,\n    pub _pad0: [u8; 12],\n    pub tint: [f32; 3],\n"));
+        assert!(bindings.rust.contains(
+            "    pub _pad1: [u8; 4],\n    pub center: [f32; 2],\n    pub _pad2: [u8; 8],\n}"
+        ));
+        assert!(bindings
+            .rust
+            .contains("assert_eq!(std::mem::size_of::<GuiControlled>(), 48);"));
+        assert!(bindings
+            .rust
+            .contains("assert_eq!(std::mem::offset_of!(GuiControlled, center), 32);"));
+
+        assert!(bindings
+            .c
+            .contains("    uint8_t _pad0[12];\n    float tint[3];\n"));
+        assert!(bindings.c.contains("offsetof(GuiControlled, tint) == 16"));
+
+        assert!(bindings
+            .typescript
+            .contains("export const GUI_CONTROLLED_SIZE = 48;"));
+        assert!(bindings
+            .typescript
+            .contains("    view.setFloat32(offset + 24, value.tint[2], true);\n"));
+        assert!(!bindings.typescript.contains("_pad"));
+    }
+
+    #[test]
+    fn host_shareable_only() {
+        let source = "struct Flags {\r\n    on: bool,\r\n};\r\n";
+        let (_, slots) = parse_struct_named(source, "Flags").unwrap();
+        let error = host_shareable("Flags", &slots).err().unwrap();
+        assert!(error == "Flags.on: bool isn't host-shareable");
+    }
+}
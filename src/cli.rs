@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+
+use super::transport::{ClockMode, FIXED_FPS};
+
+const USAGE: &str = "\
+Usage: shadey [OPTIONS]
+
+Options:
+  --fixed-fps <N>         advance time by exactly 1/N seconds per rendered frame,
+                          N from 1 to 240, whatever the playback speed
+  --emit-wgsl <SHADER>    print the composed WGSL of SHADER and exit
+  --import-shadertoy <EXPORT>
+                          write the passes of a Shadertoy JSON export as GLSL
+                          files next to it and exit
+  --bindings <SHADER>     write Rust, C and TypeScript copies of GuiControlled
+                          next to SHADER and exit
+  --struct <NAME>         struct to write bindings for instead of GuiControlled
+  --replay <FILE>         play back uniform field values recorded in FILE, one
+                          JSON object per line: {\"time\": 0.5, \"level\": 0.4}
+  -h, --help              print this help";
+
+#[derive(Debug, Default)]
+pub struct Cli {
+    pub clock_mode: Option<ClockMode>,
+    pub emit_wgsl: Option<PathBuf>,
+    pub import_shadertoy: Option<PathBuf>,
+    pub bindings: Option<PathBuf>,
+    pub bindings_struct: Option<String>,
+    pub replay: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Prints the usage and exits on bad arguments or --help
+    pub fn parse() -> Self {
+        match Self::parse_from(std::env::args().skip(1)) {
+            Ok(cli) => cli,
+            Err(e) => {
+                if !e.is_empty() {
+                    eprintln!("{e}\n");
+                }
+                eprintln!("{USAGE}");
+                std::process::exit(if e.is_empty() { 0 } else { 2 });
+            }
+        }
+    }
+
+    /// An empty error means help was asked for
+    fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Cli::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--fixed-fps" => {
+                    let fps = args
+                        .next()
+                        .and_then(|fps| fps.parse().ok())
+                        .filter(|fps| FIXED_FPS.contains(fps))
+                        .ok_or(format!(
+                            "--fixed-fps expects {} to {} frames per second",
+                            FIXED_FPS.start(),
+                            FIXED_FPS.end()
+                        ))?;
+                    cli.clock_mode = Some(ClockMode::FixedStep { fps });
+                }
+                "--emit-wgsl" => {
+                    let path = args.next().ok_or("--emit-wgsl expects a shader path")?;
+                    cli.emit_wgsl = Some(path.into());
+                }
+                "--import-shadertoy" => {
+                    let path = args
+                        .next()
+                        .ok_or("--import-shadertoy expects a Shadertoy JSON export")?;
+                    cli.import_shadertoy = Some(path.into());
+                }
+                "--bindings" => {
+                    let path = args.next().ok_or("--bindings expects a shader path")?;
+                    cli.bindings = Some(path.into());
+                }
+                "--struct" => {
+                    let name = args.next().ok_or("--struct expects a struct name")?;
+                    cli.bindings_struct = Some(name);
+                }
+                "--replay" => {
+                    let path = args.next().ok_or("--replay expects a file")?;
+                    cli.replay = Some(path.into());
+                }
+                "-h" | "--help" => return Err(String::new()),
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+
+        Ok(cli)
+    }
+}
+
+#[allow(unused)]
+mod tests {
+    use crate::{cli::Cli, transport::ClockMode};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn fixed_fps() {
+        let cli = Cli::parse_from(args(&["--fixed-fps", "30"])).unwrap();
+        assert!(cli.clock_mode == Some(ClockMode::FixedStep { fps: 30 }));
+    }
+
+    #[test]
+    fn emit_wgsl() {
+        let cli = Cli::parse_from(args(&["--emit-wgsl", "shader/voronoi.wgsl"])).unwrap();
+        assert!(cli.emit_wgsl == Some("shader/voronoi.wgsl".into()));
+        assert!(Cli::parse_from(args(&["--emit-wgsl"])).is_err());
+    }
+
+    #[test]
+    fn import_shadertoy() {
+        let cli = Cli::parse_from(args(&["--import-shadertoy", "shader/trails.json"])).unwrap();
+        assert!(cli.import_shadertoy == Some("shader/trails.json".into()));
+    }
+
+    #[test]
+    fn bindings() {
+        let cli = Cli::parse_from(args(&["--bindings", "shader/voronoi.wgsl"])).unwrap();
+        assert!(cli.bindings == Some("shader/voronoi.wgsl".into()));
+        assert!(cli.bindings_struct.is_none());
+        let cli =
+            Cli::parse_from(args(&["--bindings", "a.wgsl", "--struct", "StdUniform"])).unwrap();
+        assert!(cli.bindings_struct.as_deref() == Some("StdUniform"));
+    }
+
+    #[test]
+    fn replay() {
+        let cli = Cli::parse_from(args(&["--replay", "tilt.jsonl"])).unwrap();
+        assert!(cli.replay == Some("tilt.jsonl".into()));
+        assert!(Cli::parse_from(args(&["--replay"])).is_err());
+    }
+
+    #[test]
+    fn fixed_fps_must_be_in_range() {
+        assert!(Cli::parse_from(args(&["--fixed-fps", "0"])).is_err());
+        assert!(Cli::parse_from(args(&["--fixed-fps", "1000"])).is_err());
+        assert!(Cli::parse_from(args(&["--fixed-fps"])).is_err());
+    }
+}
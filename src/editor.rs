@@ -0,0 +1,263 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use super::shader::Diagnostic;
+
+// pause in typing after which the buffer is recompiled
+const COMPILE_DELAY: Duration = Duration::from_millis(500);
+
+const KEYWORDS: &[&str] = &[
+    "alias",
+    "break",
+    "case",
+    "const",
+    "const_assert",
+    "continue",
+    "continuing",
+    "default",
+    "diagnostic",
+    "discard",
+    "else",
+    "enable",
+    "false",
+    "fn",
+    "for",
+    "if",
+    "let",
+    "loop",
+    "override",
+    "return",
+    "struct",
+    "switch",
+    "true",
+    "var",
+    "while",
+];
+
+const TYPES: &[&str] = &[
+    "array",
+    "atomic",
+    "bool",
+    "f16",
+    "f32",
+    "i32",
+    "ptr",
+    "sampler",
+    "sampler_comparison",
+    "u32",
+    "function",
+    "private",
+    "workgroup",
+    "uniform",
+    "storage",
+    "read",
+    "write",
+    "read_write",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    Type,
+    Attribute,
+    Number,
+    Comment,
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn identifier_kind(identifier: &str) -> TokenKind {
+    let is_generated_type = ["vec", "mat", "texture_"]
+        .iter()
+        .any(|prefix| identifier.starts_with(prefix));
+    if KEYWORDS.contains(&identifier) {
+        TokenKind::Keyword
+    } else if TYPES.contains(&identifier) || is_generated_type {
+        TokenKind::Type
+    } else {
+        TokenKind::Plain
+    }
+}
+
+/// Split WGSL into highlighted ranges, covering the whole source
+pub fn tokenize(source: &str) -> Vec<(TokenKind, Range<usize>)> {
+    let mut tokens: Vec<(TokenKind, Range<usize>)> = Vec::new();
+    let mut start = 0;
+    while start < source.len() {
+        let rest = &source[start..];
+        let first = rest.chars().next().expect("Not at the end of the source");
+        let identifier_len = |from: usize| {
+            rest[from..]
+                .find(|c| !is_identifier_char(c))
+                .map_or(rest.len(), |end| end + from)
+        };
+
+        let (kind, len) = if rest.starts_with("//") {
+            (TokenKind::Comment, rest.find('\n').unwrap_or(rest.len()))
+        } else if rest.starts_with("/*") {
+            (
+                TokenKind::Comment,
+                rest.find("*/").map_or(rest.len(), |end| end + 2),
+            )
+        } else if first == '@' {
+            (TokenKind::Attribute, identifier_len(1))
+        } else if first.is_ascii_digit() {
+            // covers hex, suffixes and exponents, the dot of a float is plain
+            (TokenKind::Number, identifier_len(0))
+        } else if is_identifier_char(first) {
+            let len = identifier_len(0);
+            (identifier_kind(&rest[..len]), len)
+        } else {
+            (TokenKind::Plain, first.len_utf8())
+        };
+
+        let end = start + len;
+        match tokens.last_mut() {
+            Some((last_kind, range)) if *last_kind == kind && range.end == start => range.end = end,
+            _ => tokens.push((kind, start..end)),
+        }
+        start = end;
+    }
+    tokens
+}
+
+/// The shader being edited, recompiled live and saved on demand
+pub struct Editor {
+    pub visible: bool,
+    path: PathBuf,
+    pub buffer: String,
+    // content of the file when it was last loaded or saved
+    saved: String,
+    edited_at: Option<Instant>,
+    changed_on_disk: bool,
+    pub diagnostic: Option<Diagnostic>,
+}
+
+impl Editor {
+    pub fn open(path: &Path) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Shader {path:?} couldn't be read: {e}");
+            String::new()
+        });
+
+        Self {
+            visible: false,
+            path: path.to_owned(),
+            buffer: content.clone(),
+            saved: content,
+            edited_at: None,
+            changed_on_disk: false,
+            diagnostic: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.buffer != self.saved
+    }
+
+    pub fn changed_on_disk(&self) -> bool {
+        self.changed_on_disk
+    }
+
+    pub fn edited(&mut self) {
+        self.edited_at = Some(Instant::now());
+    }
+
+    /// True once per pause in typing
+    pub fn compile_due(&mut self) -> bool {
+        let due = self
+            .edited_at
+            .is_some_and(|edited_at| edited_at.elapsed() >= COMPILE_DELAY);
+        if due {
+            self.edited_at = None;
+        }
+        due
+    }
+
+    pub fn save(&mut self) {
+        match std::fs::write(&self.path, &self.buffer) {
+            Ok(()) => {
+                self.saved = self.buffer.clone();
+                self.changed_on_disk = false;
+            }
+            Err(e) => eprintln!("Shader couldn't be saved to {:?}: {e}", self.path),
+        }
+    }
+
+    /// Follow the file when it changed outside of the editor, unsaved edits
+    /// are kept until reloaded
+    pub fn disk_changed(&mut self) {
+        let Ok(content) = std::fs::read_to_string(&self.path) else {
+            return;
+        };
+        if content == self.saved {
+            return;
+        }
+        if self.is_dirty() {
+            self.changed_on_disk = true;
+        } else {
+            self.buffer = content.clone();
+            self.saved = content;
+        }
+    }
+
+    /// Drop the unsaved edits for what's on disk
+    pub fn reload(&mut self) {
+        let visible = self.visible;
+        *self = Self::open(&self.path.clone());
+        self.visible = visible;
+    }
+
+    /// Keep the unsaved edits over what changed on disk
+    pub fn keep_edits(&mut self) {
+        self.changed_on_disk = false;
+        self.edited();
+    }
+}
+
+#[allow(unused)]
+mod tests {
+    use crate::editor::{tokenize, TokenKind::*};
+
+    #[test]
+    fn tokens_cover_source() {
+        let source = "@fragment fn f() -> vec4<f32> { // é\n    let x = 1.5e3; }";
+        let tokens = tokenize(source);
+
+        assert!(tokens.first().unwrap().1.start == 0);
+        assert!(tokens.last().unwrap().1.end == source.len());
+        assert!(tokens.windows(2).all(|w| w[0].1.end == w[1].1.start));
+    }
+
+    #[test]
+    fn token_kinds() {
+        let source = "@group(0) var x: f32 = 0x1fu; /* a */";
+        let kinds = tokenize(source)
+            .into_iter()
+            .filter(|(kind, _)| *kind != Plain)
+            .map(|(kind, range)| (kind, &source[range]))
+            .collect::<Vec<_>>();
+
+        assert!(
+            kinds
+                == [
+                    (Attribute, "@group"),
+                    (Number, "0"),
+                    (Keyword, "var"),
+                    (Type, "f32"),
+                    (Number, "0x1fu"),
+                    (Comment, "/* a */"),
+                ]
+        );
+    }
+}
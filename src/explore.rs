@@ -0,0 +1,161 @@
+use std::{collections::BTreeSet, ops::RangeInclusive};
+
+use rand::Rng;
+
+use super::wgsl::{PType, RuntimeStruct, SlotValue, SlotValues, StructSlot, TType};
+
+const MAX_VARIANTS: usize = 32;
+
+/// Range of each component of a slot, its `range` annotation or one matching
+/// the widget the ui shows for the type
+fn component_range(slot: &StructSlot) -> RangeInclusive<f64> {
+    if let Some(range) = slot.range() {
+        return *range.start() as f64..=*range.end() as f64;
+    }
+    match slot.typed {
+        TType::Scalar(PType::U32 | PType::I32) => 0.0..=100.0,
+        TType::Vector(_, PType::U32 | PType::I32) => 0.0..=255.0,
+        _ => 0.0..=1.0,
+    }
+}
+
+/// Randomize/mutate actions over the unlocked `GuiControlled` slots, and the
+/// variants they generated
+pub struct Explorer {
+    pub locked: BTreeSet<String>,
+    /// How far a mutation goes, as a fraction of each slot's range
+    pub amount: f32,
+    variants: Vec<SlotValues>,
+}
+
+impl Default for Explorer {
+    fn default() -> Self {
+        Self {
+            locked: BTreeSet::new(),
+            amount: 0.1,
+            variants: Vec::new(),
+        }
+    }
+}
+
+impl Explorer {
+    pub fn is_locked(&self, name: &str) -> bool {
+        self.locked.contains(name)
+    }
+
+    pub fn toggle_lock(&mut self, name: &str) {
+        if !self.locked.remove(name) {
+            self.locked.insert(name.to_owned());
+        }
+    }
+
+    pub fn randomize(&mut self, gui_struct: &mut RuntimeStruct) {
+        let mut rng = rand::thread_rng();
+        self.generate(gui_struct, |_, range| rng.gen_range(range));
+    }
+
+    pub fn mutate(&mut self, gui_struct: &mut RuntimeStruct) {
+        let mut rng = rand::thread_rng();
+        let amount = self.amount as f64;
+        self.generate(gui_struct, |x, range| {
+            let spread = (range.end() - range.start()) * amount;
+            (x + rng.gen_range(-1.0..=1.0) * spread).clamp(*range.start(), *range.end())
+        });
+    }
+
+    /// Replace every unlocked component with `new(current, range)`
+    fn generate(
+        &mut self,
+        gui_struct: &mut RuntimeStruct,
+        mut new: impl FnMut(f64, RangeInclusive<f64>) -> f64,
+    ) {
+        for slot in 1..=gui_struct.slots.len() {
+            let slot_def = &gui_struct.slots[slot - 1];
+            if self.is_locked(&slot_def.identifier) {
+                continue;
+            }
+            let Some(value) = gui_struct.slot_value(slot) else {
+                continue;
+            };
+            let range = component_range(slot_def);
+            if range.start() > range.end() {
+                continue;
+            }
+
+            let components = value
+                .components()
+                .iter()
+                .map(|x| new(*x, range.clone()))
+                .collect::<Vec<_>>();
+            let value = match value {
+                SlotValue::Scalar(_) => SlotValue::Scalar(components[0]),
+                SlotValue::Vector(_) => SlotValue::Vector(components),
+            };
+            gui_struct.set_slot_value(slot, &value);
+        }
+
+        self.variants.push(gui_struct.values());
+        if self.variants.len() > MAX_VARIANTS {
+            self.variants.remove(0);
+        }
+    }
+
+    pub fn variants(&self) -> &[SlotValues] {
+        &self.variants
+    }
+}
+
+#[allow(unused)]
+mod tests {
+    use crate::{
+        explore::Explorer,
+        parser::structslot_option,
+        wgsl::{PType::*, RuntimeStruct, SlotValue, StructSlot, TType::*},
+    };
+
+    fn gui_struct() -> RuntimeStruct {
+        RuntimeStruct::new(vec![
+            StructSlot {
+                identifier: "speed".to_owned(),
+                typed: Scalar(F32),
+                options: structslot_option("range(min=2,max=3)").ok().map(|r| r.1),
+                default: None,
+            },
+            StructSlot {
+                identifier: "count".to_owned(),
+                typed: Scalar(U32),
+                options: None,
+                default: None,
+            },
+        ])
+    }
+
+    #[test]
+    fn randomize_stays_in_range() {
+        let mut explorer = Explorer::default();
+        let mut gui_struct = gui_struct();
+        for _ in 0..20 {
+            explorer.randomize(&mut gui_struct);
+            let SlotValue::Scalar(speed) = gui_struct.values()["speed"] else {
+                panic!()
+            };
+            assert!((2.0..=3.0).contains(&speed));
+        }
+        assert!(explorer.variants().len() == 20);
+    }
+
+    #[test]
+    fn locked_slots_are_kept() {
+        let mut explorer = Explorer {
+            amount: 1.0,
+            ..Default::default()
+        };
+        let mut gui_struct = gui_struct();
+        gui_struct.write_to_slot(2, &42u32);
+        explorer.toggle_lock("count");
+        explorer.randomize(&mut gui_struct);
+        explorer.mutate(&mut gui_struct);
+
+        assert!(gui_struct.values()["count"] == SlotValue::Scalar(42.0));
+    }
+}
@@ -0,0 +1,400 @@
+//! Small math expressions driving `GuiControlled` slots, e.g. `0.5 + 0.5*sin(time*2)`.
+//!
+//! Variables are the `StdUniform` and `GuiControlled` slots by name, with
+//! `.x`/`.y`/`.z`/`.w` (or `.r`/`.g`/`.b`/`.a`) picking a component of vectors.
+//! `t`, `mouse` and `resolution` are short for `time`, `mouse_pos` and
+//! `window_size`, `pi` and `tau` are constants. Comma separated expressions
+//! fill the components of vector slots, a single one is broadcast.
+
+use std::collections::BTreeMap;
+
+use nom::{
+    branch::alt,
+    character::complete::{char, multispace0, one_of},
+    combinator::{all_consuming, map, opt},
+    multi::{fold_many0, separated_list1},
+    number::complete::double,
+    sequence::{delimited, pair, preceded},
+    IResult,
+};
+
+use super::{
+    parser::{identifier, ws},
+    wgsl::{RuntimeStruct, SlotValue, SlotValues, StructSlotOptions, TType},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    Variable {
+        name: String,
+        component: Option<usize>,
+    },
+    Negate(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+fn arity(function: &str) -> Option<usize> {
+    Some(match function {
+        "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "abs" | "floor" | "ceil" | "round"
+        | "fract" | "sqrt" | "exp" | "log" | "sign" => 1,
+        "atan2" | "min" | "max" | "pow" | "step" | "mod" => 2,
+        "clamp" | "mix" | "smoothstep" => 3,
+        _ => return None,
+    })
+}
+
+fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+    Expr::Binary(op, lhs.into(), rhs.into())
+}
+
+fn variable_or_call(input: &str) -> IResult<&str, Expr> {
+    let (rest, name) = identifier(input)?;
+    if let Ok((rest, args)) =
+        delimited(ws(char('(')), separated_list1(char(','), expr), char(')'))(rest)
+    {
+        return Ok((rest, Expr::Call(name.to_owned(), args)));
+    }
+
+    let (rest, component) = opt(preceded(char('.'), one_of("xyzwrgba")))(rest)?;
+    let component = component.map(|c| match c {
+        'x' | 'r' => 0,
+        'y' | 'g' => 1,
+        'z' | 'b' => 2,
+        _ => 3,
+    });
+    Ok((
+        rest,
+        Expr::Variable {
+            name: name.to_owned(),
+            component,
+        },
+    ))
+}
+
+fn atom(input: &str) -> IResult<&str, Expr> {
+    delimited(
+        multispace0,
+        alt((
+            delimited(char('('), expr, char(')')),
+            variable_or_call,
+            map(double, Expr::Number),
+        )),
+        multispace0,
+    )(input)
+}
+
+// right associative, binds tighter than negation: -2^2 == -4
+fn power(input: &str) -> IResult<&str, Expr> {
+    let (rest, base) = atom(input)?;
+    let (rest, exponent) = opt(preceded(ws(char('^')), unary))(rest)?;
+    Ok((
+        rest,
+        match exponent {
+            Some(exponent) => binary(BinaryOp::Pow, base, exponent),
+            None => base,
+        },
+    ))
+}
+
+fn unary(input: &str) -> IResult<&str, Expr> {
+    alt((
+        map(preceded(ws(char('-')), unary), |e| Expr::Negate(e.into())),
+        power,
+    ))(input)
+}
+
+fn term(input: &str) -> IResult<&str, Expr> {
+    let (rest, first) = unary(input)?;
+    fold_many0(
+        pair(ws(one_of("*/%")), unary),
+        move || first.clone(),
+        |lhs, (op, rhs)| {
+            let op = match op {
+                '*' => BinaryOp::Mul,
+                '/' => BinaryOp::Div,
+                _ => BinaryOp::Rem,
+            };
+            binary(op, lhs, rhs)
+        },
+    )(rest)
+}
+
+fn expr(input: &str) -> IResult<&str, Expr> {
+    let (rest, first) = term(input)?;
+    fold_many0(
+        pair(ws(one_of("+-")), term),
+        move || first.clone(),
+        |lhs, (op, rhs)| {
+            let op = if op == '+' {
+                BinaryOp::Add
+            } else {
+                BinaryOp::Sub
+            };
+            binary(op, lhs, rhs)
+        },
+    )(rest)
+}
+
+impl Expr {
+    fn check(&self) -> Result<(), String> {
+        match self {
+            Expr::Number(_) | Expr::Variable { .. } => Ok(()),
+            Expr::Negate(e) => e.check(),
+            Expr::Binary(_, lhs, rhs) => lhs.check().and(rhs.check()),
+            Expr::Call(function, args) => {
+                let expected = arity(function).ok_or(format!("Unknown function {function}"))?;
+                if args.len() != expected {
+                    return Err(format!("{function} takes {expected} arguments"));
+                }
+                args.iter().try_for_each(Expr::check)
+            }
+        }
+    }
+
+    fn eval(&self, inputs: &SlotValues) -> Result<f64, String> {
+        Ok(match self {
+            Expr::Number(x) => *x,
+            Expr::Variable { name, component } => {
+                let value = match name.as_str() {
+                    "pi" => return Ok(std::f64::consts::PI),
+                    "tau" => return Ok(std::f64::consts::TAU),
+                    "t" => inputs.get("time"),
+                    "mouse" => inputs.get("mouse_pos"),
+                    "resolution" => inputs.get("window_size"),
+                    _ => inputs.get(name),
+                }
+                .ok_or(format!("Unknown variable {name}"))?
+                .components();
+
+                match component {
+                    Some(i) => *value
+                        .get(*i)
+                        .ok_or(format!("{name} has only {} components", value.len()))?,
+                    None if value.len() == 1 => value[0],
+                    None => return Err(format!("{name} is a vector, pick a component: {name}.x")),
+                }
+            }
+            Expr::Negate(e) => -e.eval(inputs)?,
+            Expr::Binary(op, lhs, rhs) => {
+                let (a, b) = (lhs.eval(inputs)?, rhs.eval(inputs)?);
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    BinaryOp::Rem => a - b * (a / b).floor(),
+                    BinaryOp::Pow => a.powf(b),
+                }
+            }
+            Expr::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| a.eval(inputs))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match (function.as_str(), &args[..]) {
+                    ("sin", [x]) => x.sin(),
+                    ("cos", [x]) => x.cos(),
+                    ("tan", [x]) => x.tan(),
+                    ("asin", [x]) => x.asin(),
+                    ("acos", [x]) => x.acos(),
+                    ("atan", [x]) => x.atan(),
+                    ("abs", [x]) => x.abs(),
+                    ("floor", [x]) => x.floor(),
+                    ("ceil", [x]) => x.ceil(),
+                    ("round", [x]) => x.round(),
+                    ("fract", [x]) => x - x.floor(),
+                    ("sqrt", [x]) => x.sqrt(),
+                    ("exp", [x]) => x.exp(),
+                    ("log", [x]) => x.ln(),
+                    ("sign", [x]) => x.signum(),
+                    ("atan2", [y, x]) => y.atan2(*x),
+                    ("min", [a, b]) => a.min(*b),
+                    ("max", [a, b]) => a.max(*b),
+                    ("pow", [a, b]) => a.powf(*b),
+                    ("step", [edge, x]) => (x >= edge) as u32 as f64,
+                    ("mod", [a, b]) => a - b * (a / b).floor(),
+                    ("clamp", [x, low, high]) => x.max(*low).min(*high),
+                    ("mix", [a, b, t]) => a + (b - a) * t,
+                    ("smoothstep", [low, high, x]) => {
+                        let t = ((x - low) / (high - low)).clamp(0.0, 1.0);
+                        t * t * (3.0 - 2.0 * t)
+                    }
+                    _ => unreachable!("arity is checked when parsing"),
+                }
+            }
+        })
+    }
+}
+
+/// Parsed once, evaluated every frame
+#[derive(Debug, Clone)]
+pub struct Expression {
+    components: Vec<Expr>,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let (_, components) =
+            all_consuming(separated_list1(char(','), expr))(source).map_err(|e| match e {
+                nom::Err::Error(e) | nom::Err::Failure(e) if !e.input.is_empty() => {
+                    format!("Unexpected {}", e.input)
+                }
+                _ => "Incomplete expression".to_owned(),
+            })?;
+        components.iter().try_for_each(Expr::check)?;
+
+        Ok(Self { components })
+    }
+
+    pub fn eval(&self, inputs: &SlotValues) -> Result<Vec<f64>, String> {
+        self.components.iter().map(|c| c.eval(inputs)).collect()
+    }
+}
+
+pub struct Binding {
+    pub source: String,
+    expression: Result<Expression, String>,
+    from_annotation: bool,
+    error: Option<String>,
+}
+
+impl Binding {
+    fn new(source: &str, from_annotation: bool) -> Self {
+        let expression = Expression::parse(source);
+        Self {
+            source: source.to_owned(),
+            error: expression.as_ref().err().cloned(),
+            expression,
+            from_annotation,
+        }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// The `GuiControlled` slots driven by an expression, by slot name
+#[derive(Default)]
+pub struct Expressions {
+    bindings: BTreeMap<String, Binding>,
+}
+
+impl Expressions {
+    pub fn get(&self, name: &str) -> Option<&Binding> {
+        self.bindings.get(name)
+    }
+
+    pub fn set(&mut self, name: &str, source: &str) {
+        self.bindings
+            .insert(name.to_owned(), Binding::new(source, false));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.bindings.remove(name);
+    }
+
+    /// Pick up the `// expr(...)` annotations of a new struct, the ones typed
+    /// in the ui are kept
+    pub fn bind_annotations(&mut self, gui_struct: &RuntimeStruct) {
+        self.bindings.retain(|_, b| !b.from_annotation);
+        for slot in &gui_struct.slots {
+            if let Some(StructSlotOptions::Expression { source }) = &slot.options {
+                self.bindings
+                    .insert(slot.identifier.clone(), Binding::new(source, true));
+            }
+        }
+    }
+
+    /// Slots read by the expressions hold the values of the previous frame
+    /// when they're driven by an expression themselves
+    pub fn apply(&mut self, std_struct: &RuntimeStruct, gui_struct: &mut RuntimeStruct) {
+        if self.bindings.is_empty() {
+            return;
+        }
+        let mut inputs = gui_struct.values();
+        inputs.extend(std_struct.values());
+
+        for (name, binding) in &mut self.bindings {
+            let Some(slot) = gui_struct.get_slot_number(name) else {
+                continue;
+            };
+            let Ok(expression) = &binding.expression else {
+                continue;
+            };
+
+            let result = expression.eval(&inputs).and_then(|components| {
+                let count = gui_struct
+                    .slot_value(slot)
+                    .map_or(0, |v| v.components().len());
+                let value = match (components.len(), &gui_struct.slots[slot - 1].typed) {
+                    (1, TType::Scalar(_)) => SlotValue::Scalar(components[0]),
+                    (1, _) => SlotValue::Vector(vec![components[0]; count]),
+                    (n, _) if n == count => SlotValue::Vector(components),
+                    (n, _) => return Err(format!("{n} values for a slot of {count} components")),
+                };
+                gui_struct
+                    .set_slot_value(slot, &value)
+                    .then_some(())
+                    .ok_or("This slot type can't be driven by an expression".to_owned())
+            });
+            binding.error = result.err();
+        }
+    }
+}
+
+#[allow(unused)]
+mod tests {
+    use crate::{
+        expr::Expression,
+        wgsl::{SlotValue, SlotValues},
+    };
+
+    fn inputs() -> SlotValues {
+        SlotValues::from([
+            ("time".to_owned(), SlotValue::Scalar(1.5)),
+            ("mouse_pos".to_owned(), SlotValue::Vector(vec![3.0, 4.0])),
+        ])
+    }
+
+    fn eval(source: &str) -> Result<Vec<f64>, String> {
+        Expression::parse(source)?.eval(&inputs())
+    }
+
+    #[test]
+    fn precedence() {
+        assert!(eval("1 + 2 * 3 ^ 2") == Ok(vec![19.0]));
+        assert!(eval("-2^2") == Ok(vec![-4.0]));
+        assert!(eval("(1 + 2) * 3 - 4 / 2") == Ok(vec![7.0]));
+        assert!(eval("-7 % 3") == Ok(vec![2.0]));
+    }
+
+    #[test]
+    fn variables_and_functions() {
+        let wave = eval("0.5 + 0.5*sin(time*2)").unwrap()[0];
+        assert!((wave - (0.5 + 0.5 * 3f64.sin())).abs() < 1e-12);
+        assert!(eval("mouse.x * 10") == Ok(vec![30.0]));
+        assert!(eval("clamp(t, 0, 1), mouse.y") == Ok(vec![1.0, 4.0]));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(eval("mouse * 2").is_err());
+        assert!(eval("foo(1)").is_err());
+        assert!(eval("min(1)").is_err());
+        assert!(eval("speed + 1").is_err());
+        assert!(eval("1 +").is_err());
+    }
+}
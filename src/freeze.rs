@@ -0,0 +1,285 @@
+//! A production copy of the shader: the `GuiControlled` values frozen into
+//! their accessors, the `_gui` binding gone, and only the declarations the
+//! entry points reach kept, std lib helpers included.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use super::{
+    shader::{ShaderFileBuf, AUTOGEN_DELIM, GUICONTROLLED_DEF},
+    wgsl::{PType, RuntimeStruct, SlotValue, TType},
+};
+
+const DECLARATIONS: [&str; 6] = ["fn", "struct", "var", "const", "override", "alias"];
+
+/// Next to the shader, `voronoi.wgsl` -> `voronoi.frozen.wgsl`
+pub fn export_path(shader_path: &Path) -> PathBuf {
+    shader_path.with_extension("frozen.wgsl")
+}
+
+/// The value as a WGSL expression, None for types without a literal
+fn literal(typed: &TType, value: &SlotValue) -> Option<String> {
+    let scalar = |ptype: &PType, x: f64| match ptype {
+        PType::F32 => Some(format!("{:?}", x as f32)),
+        PType::U32 => Some(format!("{}u", x as u32)),
+        PType::I32 => Some(format!("{}i", x as i32)),
+        _ => None,
+    };
+    match (typed, value) {
+        (TType::Scalar(ptype), SlotValue::Scalar(x)) => scalar(ptype, *x),
+        (TType::Vector(_, ptype), SlotValue::Vector(xs)) => {
+            let components = xs
+                .iter()
+                .map(|x| scalar(ptype, *x))
+                .collect::<Option<Vec<_>>>()?;
+            Some(format!(
+                "{}({})",
+                String::from(typed),
+                components.join(", ")
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// A top-level declaration, with the comments and blank lines leading to it
+struct Item<'a> {
+    text: &'a str,
+    // none for directives like `enable f16;`
+    name: Option<&'a str>,
+    entry_point: bool,
+    identifiers: HashSet<&'a str>,
+}
+
+fn items(source: &str) -> Vec<Item<'_>> {
+    let bytes = source.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let mut items = Vec::new();
+    let mut item = Item {
+        text: "",
+        name: None,
+        entry_point: false,
+        identifiers: HashSet::new(),
+    };
+    let (mut start, mut depth, mut i) = (0, 0, 0);
+    let mut declared = false;
+    let mut in_template = false;
+
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        if rest.starts_with(b"//") {
+            i += rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+            continue;
+        }
+        if rest.starts_with(b"/*") {
+            i += rest
+                .windows(2)
+                .position(|w| w == b"*/")
+                .map_or(rest.len(), |end| end + 2);
+            continue;
+        }
+
+        let b = bytes[i];
+        if b.is_ascii_digit() {
+            while i < bytes.len() && (is_ident(bytes[i]) || bytes[i] == b'.') {
+                i += 1;
+            }
+            continue;
+        }
+        if is_ident(b) {
+            let end = i + rest
+                .iter()
+                .position(|b| !is_ident(*b))
+                .unwrap_or(rest.len());
+            let ident = &source[i..end];
+            let attribute = i > 0 && bytes[i - 1] == b'@';
+            if attribute {
+                item.entry_point |= matches!(ident, "vertex" | "fragment" | "compute");
+            } else if item.name.is_none() && depth == 0 && !in_template {
+                match declared {
+                    true => item.name = Some(ident),
+                    false => declared = DECLARATIONS.contains(&ident),
+                }
+            }
+            // members and typed declarations don't refer to anything by their name
+            let member = i > 0 && bytes[i - 1] == b'.';
+            let declaration = source[end..].trim_start().starts_with(':');
+            if !member && !declaration {
+                item.identifiers.insert(ident);
+            }
+            i = end;
+            continue;
+        }
+
+        let mut end_item = false;
+        match b {
+            b'<' if declared && item.name.is_none() => in_template = true,
+            b'>' => in_template = false,
+            b'{' | b'(' | b'[' => depth += 1,
+            b')' | b']' => depth -= 1,
+            b'}' => {
+                depth -= 1;
+                end_item = depth == 0;
+                // `struct A {...};`
+                let trailing = &source[i + 1..];
+                if end_item && trailing.trim_start().starts_with(';') {
+                    i += 1 + trailing.find(';').unwrap();
+                }
+            }
+            b';' => end_item = depth == 0,
+            _ => (),
+        }
+        i += 1;
+
+        if end_item {
+            item.text = &source[start..i];
+            if let Some(name) = item.name {
+                item.identifiers.remove(name);
+            }
+            items.push(std::mem::replace(
+                &mut item,
+                Item {
+                    text: "",
+                    name: None,
+                    entry_point: false,
+                    identifiers: HashSet::new(),
+                },
+            ));
+            start = i;
+            declared = false;
+            in_template = false;
+        }
+    }
+    items
+}
+
+/// Only the declarations reachable from the entry points, in their order
+fn tree_shake(source: &str) -> String {
+    let items = items(source);
+    let by_name: HashMap<&str, usize> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| Some((item.name?, i)))
+        .collect();
+
+    let mut kept = vec![false; items.len()];
+    let mut pending: Vec<usize> = (0..items.len())
+        .filter(|i| items[*i].entry_point || items[*i].name.is_none())
+        .collect();
+    while let Some(i) = pending.pop() {
+        if std::mem::replace(&mut kept[i], true) {
+            continue;
+        }
+        pending.extend(
+            items[i]
+                .identifiers
+                .iter()
+                .filter_map(|ident| by_name.get(ident)),
+        );
+    }
+
+    items
+        .iter()
+        .zip(kept)
+        .filter(|(_, kept)| *kept)
+        .map(|(item, _)| item.text)
+        .collect()
+}
+
+/// The frozen shader, validated by naga. `origin` names the shader it comes
+/// from in the header, which lists the bindings left to feed.
+pub fn freeze(
+    source: &ShaderFileBuf,
+    gui_struct: &RuntimeStruct,
+    origin: &str,
+) -> Result<String, String> {
+    let mut content = source.as_ref().to_owned();
+    let mut values = Vec::new();
+    for (i, slot) in gui_struct.slots.iter().enumerate() {
+        let value = gui_struct
+            .slot_value(i + 1)
+            .and_then(|value| literal(&slot.typed, &value))
+            .ok_or_else(|| {
+                format!(
+                    "{}: {} values can't be frozen",
+                    slot.identifier,
+                    String::from(&slot.typed)
+                )
+            })?;
+        let frozen = format!(
+            "fn {ident}() -> {typed} {{return {value};}}",
+            ident = slot.identifier,
+            typed = String::from(&slot.typed)
+        );
+        content = content.replacen(&slot.generate_definition(), &frozen, 1);
+        values.push(value);
+    }
+    // kept only when the shader reads `_gui` directly
+    let constant = format!("const _gui = GuiControlled({});", values.join(", "));
+    let content = content
+        .replacen(GUICONTROLLED_DEF, &constant, 1)
+        .replace(&format!("{AUTOGEN_DELIM}\n"), "");
+    let content = tree_shake(&content);
+
+    let module = naga::front::wgsl::parse_str(&content).map_err(|e| e.emit_to_string(&content))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|e| e.emit_to_string(&content))?;
+
+    let mut header = format!("// Frozen from {origin}, GuiControlled values are constants\n");
+    let mut bindings: Vec<_> = module
+        .global_variables
+        .iter()
+        .filter_map(|(_, var)| Some((var.binding.as_ref()?, var.name.as_deref()?)))
+        .collect();
+    bindings.sort_by_key(|(binding, _)| (binding.group, binding.binding));
+    if !bindings.is_empty() {
+        header += "// Bindings left to feed:\n";
+    }
+    for (binding, name) in bindings {
+        header += &format!(
+            "//   @group({}) @binding({}) {name}\n",
+            binding.group, binding.binding
+        );
+    }
+    Ok(header + content.trim_start())
+}
+
+#[allow(unused)]
+mod tests {
+    use crate::{
+        freeze::{freeze, tree_shake},
+        shader::compose_source,
+    };
+
+    #[test]
+    fn unreachable_declarations_are_dropped() {
+        let source = "struct A {\n    x: f32,\n};\n\n// unused\nfn b() -> f32 {\n    return 1.0;\n}\n\nfn c() -> A {\n    return A(2.0);\n}\n\n@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4<f32>(c().x);\n}\n";
+        let shaken = tree_shake(source);
+        assert!(shaken.contains("struct A"));
+        assert!(shaken.contains("fn c()"));
+        assert!(!shaken.contains("fn b()"));
+        assert!(!shaken.contains("unused"));
+    }
+
+    #[test]
+    fn values_are_frozen() {
+        let source = "struct GuiControlled {\r\n    speed: f32, // range(min=0,max=4) default(2)\r\n    tint: vec3<f32>, // default(1, 0.5, 0.25)\r\n    count: u32, // default(3)\r\n};\r\n\r\n@fragment\r\nfn fs_main(vo: VertexOutput) -> @location(0) vec4<f32> {\r\n    return vec4<f32>(tint() * speed() * time(), f32(_gui.count));\r\n}\r\n";
+        let (shader, gui_struct) = compose_source(source).unwrap();
+
+        let frozen = freeze(&shader, &gui_struct, "frozen.wgsl").unwrap();
+        assert!(frozen.contains("fn speed() -> f32 {return 2.0;}"));
+        assert!(frozen.contains("fn tint() -> vec3<f32> {return vec3<f32>(1.0, 0.5, 0.25);}"));
+        assert!(frozen.contains("const _gui = GuiControlled(2.0, vec3<f32>(1.0, 0.5, 0.25), 3u);"));
+        assert!(!frozen.contains("var<uniform> _gui"));
+        assert!(frozen.contains("fn time()"));
+        assert!(!frozen.contains("fn hex_to_rgba"));
+        assert!(frozen.contains("//   @group(0) @binding(0) _std_uniform\n"));
+        assert!(!frozen.contains("_keyboard"));
+    }
+}
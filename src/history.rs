@@ -0,0 +1,180 @@
+use super::wgsl::{RuntimeStruct, SlotValues};
+
+const MAX_EDITS: usize = 100;
+
+struct Edit {
+    // empty when it should be named after the edited slots
+    label: String,
+    before: SlotValues,
+    after: SlotValues,
+}
+
+impl Edit {
+    fn label(&self) -> String {
+        if self.label.is_empty() {
+            self.after.keys().cloned().collect::<Vec<_>>().join(", ")
+        } else {
+            self.label.clone()
+        }
+    }
+}
+
+/// Undo/redo of the values edited in the ui. Only the slots an edit changed
+/// are restored, so animated slots aren't frozen by an undo.
+#[derive(Default)]
+pub struct History {
+    edits: Vec<Edit>,
+    // number of edits currently applied
+    position: usize,
+    // a drag or a text input still going on extends the last edit
+    gesture_open: bool,
+    pending_label: Option<String>,
+    // the frame's changes come from undo/redo, not from the user
+    skip_record: bool,
+}
+
+impl History {
+    /// Name the next recorded edit, e.g. "preset calm" instead of the slot names
+    pub fn label_next(&mut self, label: impl Into<String>) {
+        self.pending_label = Some(label.into());
+    }
+
+    /// Called once per frame with the values from before and after the ui ran
+    pub fn record(&mut self, before: &SlotValues, after: &SlotValues, gesture_ongoing: bool) {
+        let label = self.pending_label.take();
+        if std::mem::take(&mut self.skip_record) {
+            self.gesture_open = false;
+            return;
+        }
+
+        let changed = after
+            .iter()
+            .filter(|(name, value)| before.get(*name).is_some_and(|b| b != *value))
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            self.gesture_open &= gesture_ongoing;
+            return;
+        }
+
+        let extend = self.gesture_open && label.is_none() && self.position == self.edits.len();
+        if !extend {
+            self.edits.truncate(self.position);
+            self.edits.push(Edit {
+                label: label.unwrap_or_default(),
+                before: SlotValues::new(),
+                after: SlotValues::new(),
+            });
+            if self.edits.len() > MAX_EDITS {
+                self.edits.remove(0);
+            }
+            self.position = self.edits.len();
+        }
+
+        let edit = self.edits.last_mut().expect("An edit was just pushed");
+        for (name, value) in changed {
+            edit.before
+                .entry(name.clone())
+                .or_insert_with(|| before[name].clone());
+            edit.after.insert(name.clone(), value.clone());
+        }
+        self.gesture_open = gesture_ongoing;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.position > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.position < self.edits.len()
+    }
+
+    pub fn undo(&mut self, gui_struct: &mut RuntimeStruct) {
+        if self.can_undo() {
+            self.jump_to(self.position - 1, gui_struct);
+        }
+    }
+
+    pub fn redo(&mut self, gui_struct: &mut RuntimeStruct) {
+        if self.can_redo() {
+            self.jump_to(self.position + 1, gui_struct);
+        }
+    }
+
+    /// Undo or redo until `position` edits are applied
+    pub fn jump_to(&mut self, position: usize, gui_struct: &mut RuntimeStruct) {
+        let position = position.min(self.edits.len());
+        while self.position > position {
+            self.position -= 1;
+            gui_struct.apply_values(&self.edits[self.position].before);
+        }
+        while self.position < position {
+            gui_struct.apply_values(&self.edits[self.position].after);
+            self.position += 1;
+        }
+        self.gesture_open = false;
+        self.skip_record = true;
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn labels(&self) -> impl Iterator<Item = String> + '_ {
+        self.edits.iter().map(Edit::label)
+    }
+}
+
+#[allow(unused)]
+mod tests {
+    use crate::{
+        history::History,
+        wgsl::{PType::*, RuntimeStruct, SlotValue, SlotValues, StructSlot, TType::*},
+    };
+
+    fn values(speed: f64) -> SlotValues {
+        SlotValues::from([("speed".to_owned(), SlotValue::Scalar(speed))])
+    }
+
+    #[test]
+    fn drag_is_a_single_step() {
+        let mut gui_struct = RuntimeStruct::new(vec![StructSlot {
+            identifier: "speed".to_owned(),
+            typed: Scalar(F32),
+            options: None,
+            default: None,
+        }]);
+        let mut history = History::default();
+        history.record(&values(0.0), &values(0.25), true);
+        history.record(&values(0.25), &values(0.5), true);
+        history.record(&values(0.5), &values(0.5), false);
+        history.record(&values(0.5), &values(1.0), false);
+
+        assert!(history.labels().count() == 2);
+        history.undo(&mut gui_struct);
+        assert!(gui_struct.values() == values(0.5));
+        history.undo(&mut gui_struct);
+        assert!(gui_struct.values() == values(0.0));
+        history.redo(&mut gui_struct);
+        assert!(gui_struct.values() == values(0.5));
+    }
+
+    #[test]
+    fn new_edit_drops_redo() {
+        let mut gui_struct = RuntimeStruct::new(vec![StructSlot {
+            identifier: "speed".to_owned(),
+            typed: Scalar(F32),
+            options: None,
+            default: None,
+        }]);
+        let mut history = History::default();
+        history.record(&values(0.0), &values(1.0), false);
+        history.undo(&mut gui_struct);
+        // the undo itself isn't recorded
+        history.record(&values(1.0), &values(0.0), false);
+        history.label_next("preset calm");
+        history.record(&values(0.0), &values(2.0), false);
+
+        assert!(!history.can_redo());
+        assert!(history.labels().collect::<Vec<_>>() == ["preset calm"]);
+    }
+}
@@ -0,0 +1,531 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+
+use super::{
+    shader::UniformKind,
+    texture::Texture,
+    transport::Transport,
+    wgsl::{RuntimeStruct, Sized},
+};
+
+// pixel deltas (touchpads) are converted so scroll() is always in lines
+const PIXELS_PER_LINE: f32 = 20.0;
+
+pub struct EventContext {
+    pub window_size: [u32; 2],
+    pub egui_wants_keyboard: bool,
+}
+
+/// Providers are ticked in registration order. The clock comes first and sets
+/// `time`/`delta_time` so the following ones see this frame's values.
+pub struct FrameContext<'a> {
+    pub queue: &'a wgpu::Queue,
+    pub textures: &'a mut [Texture],
+    pub transport: &'a mut Transport,
+    pub window_size: [u32; 2],
+    pub time: f32,
+    pub delta_time: f32,
+}
+
+/// Something feeding the shader: it owns uniform fields, found by name in the
+/// `StdUniform` or the `GuiControlled` struct, and/or updates gpu resources.
+pub trait InputProvider {
+    /// Names of the uniform fields written with `field_bytes` after each tick,
+    /// bytes of another size than the field's are skipped
+    fn fields(&self) -> Vec<&str>;
+
+    fn field_bytes(&self, field: &str) -> Option<&[u8]>;
+
+    /// Returns true when the event was consumed
+    fn handle_event(&mut self, _event: &WindowEvent, _ctx: &EventContext) -> bool {
+        false
+    }
+
+    fn tick(&mut self, _ctx: &mut FrameContext) {}
+
+    /// Called once the uniforms of the frame are written
+    fn end_frame(&mut self) {}
+}
+
+struct FieldBinding {
+    provider: usize,
+    field: String,
+    uniform: UniformKind,
+    slot: usize,
+}
+
+/// The registered providers and where each of their fields lives in the uniforms
+pub struct InputProviders {
+    providers: Vec<Box<dyn InputProvider>>,
+    bindings: Vec<FieldBinding>,
+}
+
+impl InputProviders {
+    pub fn new(providers: Vec<Box<dyn InputProvider>>) -> Self {
+        Self {
+            providers,
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Call `bind` afterwards so the provider's fields get written
+    pub fn push(&mut self, provider: Box<dyn InputProvider>) {
+        self.providers.push(provider);
+    }
+
+    /// Resolve every provided field to a uniform slot, to redo when a struct changes
+    pub fn bind(&mut self, std_struct: &RuntimeStruct, gui_struct: &RuntimeStruct) {
+        self.bindings.clear();
+        for (provider, fields) in self.providers.iter().map(|p| p.fields()).enumerate() {
+            for field in fields {
+                let binding = std_struct
+                    .get_slot_number(field)
+                    .map(|slot| (UniformKind::StandardLib, slot))
+                    .or_else(|| {
+                        gui_struct
+                            .get_slot_number(field)
+                            .map(|slot| (UniformKind::GuiControlled, slot))
+                    });
+                let Some((uniform, slot)) = binding else {
+                    eprintln!("Input field {field} isn't in StdUniform nor GuiControlled");
+                    continue;
+                };
+
+                self.bindings.push(FieldBinding {
+                    provider,
+                    field: field.to_owned(),
+                    uniform,
+                    slot,
+                });
+            }
+        }
+
+        for slot in &std_struct.slots {
+            if !self
+                .bindings
+                .iter()
+                .any(|b| b.uniform == UniformKind::StandardLib && b.field == slot.identifier)
+            {
+                eprintln!("StdUniform slot {} has no provider", slot.identifier);
+            }
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent, ctx: &EventContext) -> bool {
+        let mut consumed = false;
+        for provider in &mut self.providers {
+            // every provider sees the event, even once one consumed it
+            consumed |= provider.handle_event(event, ctx);
+        }
+        consumed
+    }
+
+    pub fn tick(&mut self, ctx: &mut FrameContext) {
+        for provider in &mut self.providers {
+            provider.tick(ctx);
+        }
+    }
+
+    pub fn write(&self, std_struct: &mut RuntimeStruct, gui_struct: &mut RuntimeStruct) {
+        for binding in &self.bindings {
+            let Some(bytes) = self.providers[binding.provider].field_bytes(&binding.field) else {
+                continue;
+            };
+            let runtime_struct = match binding.uniform {
+                UniformKind::StandardLib => &mut *std_struct,
+                UniformKind::GuiControlled => &mut *gui_struct,
+            };
+            if bytes.len() == runtime_struct.slots[binding.slot - 1].typed.size() {
+                runtime_struct.write_bytes_to_slot(binding.slot, bytes);
+            }
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        for provider in &mut self.providers {
+            provider.end_frame();
+        }
+    }
+}
+
+/// Shader time, advanced by the transport according to its clock mode
+pub struct Clock {
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+    date: [f32; 4],
+    last_tick: Instant,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            time: 0.0,
+            delta_time: 0.0,
+            frame: 0,
+            date: [0.0; 4],
+            last_tick: Instant::now(),
+        }
+    }
+}
+
+impl InputProvider for Clock {
+    fn fields(&self) -> Vec<&str> {
+        vec!["time", "delta_time", "frame", "date"]
+    }
+
+    fn field_bytes(&self, field: &str) -> Option<&[u8]> {
+        Some(match field {
+            "time" => bytemuck::bytes_of(&self.time),
+            "delta_time" => bytemuck::bytes_of(&self.delta_time),
+            "frame" => bytemuck::bytes_of(&self.frame),
+            "date" => bytemuck::bytes_of(&self.date),
+            _ => return None,
+        })
+    }
+
+    fn tick(&mut self, ctx: &mut FrameContext) {
+        let now = Instant::now();
+        let real_delta = (now - self.last_tick).as_secs_f32();
+        self.last_tick = now;
+
+        self.delta_time = ctx.transport.advance(real_delta);
+        self.time = ctx.transport.time;
+        self.frame = ctx.transport.frame;
+        self.date = utc_date(SystemTime::now());
+
+        ctx.time = self.time;
+        ctx.delta_time = self.delta_time;
+    }
+}
+
+#[derive(Default)]
+pub struct WindowSize {
+    window_size: [u32; 2],
+}
+
+impl InputProvider for WindowSize {
+    fn fields(&self) -> Vec<&str> {
+        vec!["window_size"]
+    }
+
+    fn field_bytes(&self, field: &str) -> Option<&[u8]> {
+        (field == "window_size").then(|| bytemuck::bytes_of(&self.window_size))
+    }
+
+    fn tick(&mut self, ctx: &mut FrameContext) {
+        self.window_size = ctx.window_size;
+    }
+}
+
+#[derive(Default)]
+pub struct Mouse {
+    mouse_pos: [u32; 2],
+    toggle_mouse_pos: [u32; 2],
+    mouse_buttons: [u32; 4],
+    mouse_click: [f32; 4],
+    scroll: [f32; 2],
+}
+
+impl InputProvider for Mouse {
+    fn fields(&self) -> Vec<&str> {
+        vec![
+            "mouse_pos",
+            "toggle_mouse_pos",
+            "mouse_buttons",
+            "mouse_click",
+            "scroll",
+        ]
+    }
+
+    fn field_bytes(&self, field: &str) -> Option<&[u8]> {
+        Some(match field {
+            "mouse_pos" => bytemuck::bytes_of(&self.mouse_pos),
+            "toggle_mouse_pos" => bytemuck::bytes_of(&self.toggle_mouse_pos),
+            "mouse_buttons" => bytemuck::bytes_of(&self.mouse_buttons),
+            "mouse_click" => bytemuck::bytes_of(&self.mouse_click),
+            "scroll" => bytemuck::bytes_of(&self.scroll),
+            _ => return None,
+        })
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent, ctx: &EventContext) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse_pos = (*position).into();
+                if self.mouse_buttons[0] != 0 {
+                    let [x, y] = self.shadertoy_mouse_pos(ctx.window_size);
+                    self.mouse_click[0] = x;
+                    self.mouse_click[1] = y;
+                }
+                true
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let index = match button {
+                    MouseButton::Left => 0,
+                    MouseButton::Right => 1,
+                    MouseButton::Middle => 2,
+                    MouseButton::Other(_) => 3,
+                };
+                let pressed = *state == ElementState::Pressed;
+                self.mouse_buttons[index] = pressed as u32;
+
+                if *button == MouseButton::Left {
+                    if pressed {
+                        self.toggle_mouse_pos = self.mouse_pos;
+                        let [x, y] = self.shadertoy_mouse_pos(ctx.window_size);
+                        self.mouse_click = [x, y, x, y];
+                    } else {
+                        self.mouse_click[2] = -self.mouse_click[2].abs();
+                    }
+                }
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (x, y) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => (*x, *y),
+                    MouseScrollDelta::PixelDelta(p) => {
+                        (p.x as f32 / PIXELS_PER_LINE, p.y as f32 / PIXELS_PER_LINE)
+                    }
+                };
+                self.scroll[0] += x;
+                self.scroll[1] += y;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn end_frame(&mut self) {
+        // w is only positive on the frame of the click
+        self.mouse_click[3] = -self.mouse_click[3].abs();
+    }
+}
+
+impl Mouse {
+    // Shadertoy's iMouse has its origin at the bottom left
+    fn shadertoy_mouse_pos(&self, window_size: [u32; 2]) -> [f32; 2] {
+        [
+            self.mouse_pos[0] as f32,
+            window_size[1] as f32 - self.mouse_pos[1] as f32,
+        ]
+    }
+}
+
+/// Re-analyses the audio textures at the clock's time
+pub struct Audio;
+
+impl InputProvider for Audio {
+    fn fields(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    fn field_bytes(&self, _field: &str) -> Option<&[u8]> {
+        None
+    }
+
+    fn tick(&mut self, ctx: &mut FrameContext) {
+        for texture in ctx.textures.iter_mut() {
+            texture.update_audio(ctx.queue, ctx.time);
+        }
+    }
+}
+
+struct Sample {
+    time: f32,
+    values: Vec<(String, Vec<f32>)>,
+}
+
+/// Field values recorded over time, e.g. sensor data, played back at the
+/// clock's time. One JSON object per line, `time` in seconds and every other
+/// key a field: `{"time": 0.5, "tilt": [0.1, 0.2], "level": 0.4}`. Values are
+/// f32s, a field keeps its last value until a later line sets it again.
+pub struct Replay {
+    fields: Vec<String>,
+    // sorted by time
+    samples: Vec<Sample>,
+    next: usize,
+    time: f32,
+    values: HashMap<String, Vec<f32>>,
+}
+
+impl Replay {
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Replay file {path:?} couldn't be read: {e}"))?;
+        Self::parse(&content).map_err(|e| format!("Replay file {path:?}: {e}"))
+    }
+
+    fn parse(content: &str) -> Result<Self, String> {
+        let mut fields: Vec<String> = Vec::new();
+        let mut samples = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |message: &str| format!("line {}: {message}", i + 1);
+            let object: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(line).map_err(|e| error(&e.to_string()))?;
+
+            let mut time = None;
+            let mut values = Vec::new();
+            for (key, value) in object {
+                let numbers = match &value {
+                    serde_json::Value::Array(items) => items.iter().map(|x| x.as_f64()).collect(),
+                    x => x.as_f64().map(|x| vec![x]),
+                }
+                .ok_or_else(|| error(&format!("{key} isn't a number or an array of numbers")))?;
+                let numbers: Vec<f32> = numbers.into_iter().map(|x| x as f32).collect();
+
+                if key == "time" {
+                    time = numbers.first().copied();
+                    continue;
+                }
+                if !fields.contains(&key) {
+                    fields.push(key.clone());
+                }
+                values.push((key, numbers));
+            }
+            let time = time.ok_or_else(|| error("time is missing"))?;
+            samples.push(Sample { time, values });
+        }
+        samples.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        Ok(Self {
+            fields,
+            samples,
+            next: 0,
+            time: 0.0,
+            values: HashMap::new(),
+        })
+    }
+
+    /// Applies the samples up to `time`, from the start again when time went back
+    fn seek(&mut self, time: f32) {
+        if time < self.time {
+            self.next = 0;
+            self.values.clear();
+        }
+        self.time = time;
+        while let Some(sample) = self.samples.get(self.next) {
+            if sample.time > time {
+                break;
+            }
+            for (field, value) in &sample.values {
+                self.values.insert(field.clone(), value.clone());
+            }
+            self.next += 1;
+        }
+    }
+}
+
+impl InputProvider for Replay {
+    fn fields(&self) -> Vec<&str> {
+        self.fields.iter().map(String::as_str).collect()
+    }
+
+    fn field_bytes(&self, field: &str) -> Option<&[u8]> {
+        self.values
+            .get(field)
+            .map(|value| bytemuck::cast_slice(value.as_slice()))
+    }
+
+    fn tick(&mut self, ctx: &mut FrameContext) {
+        self.seek(ctx.time);
+    }
+}
+
+/// Year, month (1-12), day and seconds since midnight, in UTC
+fn utc_date(now: SystemTime) -> [f32; 4] {
+    let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let days = (since_epoch.as_secs() / 86400) as i64;
+    let seconds = since_epoch.as_secs_f64() - (days * 86400) as f64;
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    [year as f32, month as f32, day as f32, seconds as f32]
+}
+
+#[allow(unused)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::{
+        inputs::{utc_date, InputProvider, InputProviders, Replay},
+        parser::parse_struct_named,
+        wgsl::{RuntimeStruct, SlotValue},
+    };
+
+    fn runtime_struct(source: &str, name: &str) -> RuntimeStruct {
+        RuntimeStruct::new(parse_struct_named(source, name).unwrap().1)
+    }
+
+    #[test]
+    fn date_from_timestamp() {
+        // 2024-02-29 12:00:30 UTC
+        let date = utc_date(UNIX_EPOCH + Duration::from_secs(1709208030));
+        assert!(date == [2024.0, 2.0, 29.0, 43230.0]);
+    }
+
+    #[test]
+    fn date_at_epoch() {
+        assert!(utc_date(UNIX_EPOCH) == [1970.0, 1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn fields_are_written_where_they_live() {
+        let std_struct = "struct StdUniform {\r\n    time: f32,\r\n    level: f32,\r\n};\r\n";
+        let gui_struct = "struct GuiControlled {\r\n    level: f32,\r\n    tilt: vec2<f32>,\r\n    size: f32,\r\n};\r\n";
+        let mut std_struct = runtime_struct(std_struct, "StdUniform");
+        let mut gui_struct = runtime_struct(gui_struct, "GuiControlled");
+
+        let mut replay = Replay::parse(
+            "{\"time\": 0, \"level\": 2, \"tilt\": [0.5, 0.25], \"missing\": 1}\n\
+             {\"time\": 1, \"size\": [1, 2, 3]}\n",
+        )
+        .unwrap();
+        replay.seek(1.0);
+        let mut inputs = InputProviders::new(vec![Box::new(replay)]);
+        inputs.bind(&std_struct, &gui_struct);
+        inputs.write(&mut std_struct, &mut gui_struct);
+
+        // StdUniform owns the fields both structs have
+        assert!(std_struct.slot_value(2) == Some(SlotValue::Scalar(2.0)));
+        assert!(gui_struct.slot_value(1) == Some(SlotValue::Scalar(0.0)));
+        assert!(gui_struct.slot_value(2) == Some(SlotValue::Vector(vec![0.5, 0.25])));
+        // three components don't fit a f32, nothing is written
+        assert!(gui_struct.slot_value(3) == Some(SlotValue::Scalar(0.0)));
+    }
+
+    #[test]
+    fn replay_follows_time() {
+        let mut replay =
+            Replay::parse("{\"time\": 2, \"level\": 3}\n\n{\"time\": 0, \"level\": 1}\n").unwrap();
+        assert!(replay.fields() == ["level"]);
+        assert!(replay.field_bytes("level").is_none());
+
+        replay.seek(0.5);
+        assert!(replay.field_bytes("level") == Some(bytemuck::bytes_of(&1.0f32)));
+        replay.seek(2.0);
+        assert!(replay.field_bytes("level") == Some(bytemuck::bytes_of(&3.0f32)));
+        replay.seek(1.0);
+        assert!(replay.field_bytes("level") == Some(bytemuck::bytes_of(&1.0f32)));
+
+        assert!(Replay::parse("{\"level\": 1}").is_err());
+        assert!(Replay::parse("{\"time\": 0, \"level\": \"high\"}").is_err());
+    }
+}
@@ -0,0 +1,419 @@
+//! ISF (Interactive Shader Format) fragment shaders, `.fs` files starting with
+//! a JSON comment declaring their INPUTS, PASSES and IMPORTED images. The
+//! inputs become `GuiControlled` slots, with MIN/MAX/DEFAULT as slot options,
+//! images become textures, and the GLSL is translated like Shadertoy's.
+//!
+//! Not supported: audio inputs, custom vertex shaders, and passes past the
+//! first, their targets are read as still images instead.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{
+    shader::Diagnostic,
+    shadertoy::{reserved_names, sampler_define, std_block, translate, wgsl_header, TIME_OFFSET},
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "UPPERCASE")]
+struct Header {
+    inputs: Vec<Input>,
+    passes: Vec<Pass>,
+    imported: Imported,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct Input {
+    name: String,
+    #[serde(rename = "TYPE")]
+    kind: String,
+    default: Option<Value>,
+    min: Option<Value>,
+    max: Option<Value>,
+    values: Option<Vec<f64>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "UPPERCASE")]
+struct Pass {
+    target: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Imported {
+    Named(BTreeMap<String, ImportedImage>),
+    Listed(Vec<ImportedImage>),
+}
+
+impl Default for Imported {
+    fn default() -> Self {
+        Imported::Named(BTreeMap::new())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct ImportedImage {
+    name: Option<String>,
+    // one path, or the six faces of a cube
+    path: Value,
+    #[serde(rename = "TYPE")]
+    kind: Option<String>,
+}
+
+pub fn is_isf(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "fs")
+}
+
+/// The JSON of the leading `/*{ ... }*/` comment
+fn header(source: &str) -> Option<&str> {
+    let start = source.trim_start().strip_prefix("/*")?;
+    let end = start.find("*/")?;
+    Some(&start[..end])
+}
+
+/// Numbers of a JSON value, booleans count as 0 or 1
+fn numbers(value: &Value) -> Option<Vec<f64>> {
+    match value {
+        Value::Number(x) => Some(vec![x.as_f64()?]),
+        Value::Bool(x) => Some(vec![*x as u8 as f64]),
+        Value::Array(xs) => xs.iter().map(|x| x.as_f64()).collect(),
+        _ => None,
+    }
+}
+
+fn list(xs: &[f64]) -> String {
+    xs.iter().map(f64::to_string).collect::<Vec<_>>().join(", ")
+}
+
+// what an input turns into
+struct Declarations {
+    // GuiControlled members, in WGSL and in the GLSL block
+    slots: Vec<String>,
+    members: Vec<String>,
+    // GLSL defines standing in for the ISF uniforms
+    defines: Vec<String>,
+    // `// Shadey` options, and the sampler type of each texture
+    options: Vec<String>,
+    samplers: Vec<(String, &'static str)>,
+    notes: Vec<String>,
+}
+
+fn declare_input(
+    input: &Input,
+    reserved: &HashSet<String>,
+    dir: &Path,
+    declared: &mut Declarations,
+) {
+    let name = &input.name;
+    // slots get a getter next to the std lib functions
+    let slot = match reserved.contains(name) {
+        true => format!("{name}_st"),
+        false => name.clone(),
+    };
+    let bound = |value: &Option<Value>, pick: fn(f64, f64) -> f64| {
+        let xs = numbers(value.as_ref()?)?;
+        xs.into_iter().reduce(pick)
+    };
+    let (mut min, mut max) = (bound(&input.min, f64::min), bound(&input.max, f64::max));
+    let default = input.default.as_ref().and_then(numbers);
+
+    let (wgsl_type, glsl_type, define) = match input.kind.as_str() {
+        "float" => ("f32", "float", format!("shadey_gui.{slot}")),
+        "bool" | "event" => {
+            if input.kind == "event" {
+                declared
+                    .notes
+                    .push(format!("{name}: events are held until unchecked"));
+            }
+            (min, max) = (Some(0.0), Some(1.0));
+            ("u32", "uint", format!("(shadey_gui.{slot} != 0u)"))
+        }
+        "long" => {
+            if let Some(values) = &input.values {
+                min = values.iter().copied().reduce(f64::min);
+                max = values.iter().copied().reduce(f64::max);
+            }
+            if min.is_some_and(|min| min < 0.0) {
+                declared
+                    .notes
+                    .push(format!("{name}: negative values are clamped to 0"));
+                min = Some(0.0);
+            }
+            ("u32", "uint", format!("int(shadey_gui.{slot})"))
+        }
+        "color" => ("vec4<f32>", "vec4", format!("shadey_gui.{slot}")),
+        "point2D" => ("vec2<f32>", "vec2", format!("shadey_gui.{slot}")),
+        "image" => {
+            let path = dir.join(format!("{name}.png")).display().to_string();
+            declared.notes.push(format!(
+                "{name}: the image is read from {path}, transparent until there is one"
+            ));
+            declared
+                .options
+                .push(format!("texture(path={path}, name={name}, vflip=true)"));
+            declared.samplers.push((name.clone(), "2D"));
+            return;
+        }
+        kind => {
+            declared
+                .notes
+                .push(format!("{name}: {kind} inputs aren't supported"));
+            return;
+        }
+    };
+
+    let mut options = Vec::new();
+    if let (Some(min), Some(max)) = (min, max) {
+        options.push(format!("range(min={min},max={max})"));
+    }
+    if let Some(default) = default {
+        options.push(format!("default({})", list(&default)));
+    }
+    let comment = match options.is_empty() {
+        true => String::new(),
+        false => format!(" // {}", options.join(" ")),
+    };
+    declared
+        .slots
+        .push(format!("{slot}: {wgsl_type},{comment}"));
+    declared.members.push(format!("    {glsl_type} {slot};\n"));
+    declared.defines.push(format!("#define {name} {define}\n"));
+}
+
+fn declare_imported(name: &str, image: &ImportedImage, dir: &Path, declared: &mut Declarations) {
+    let path = |value: &Value| {
+        value
+            .as_str()
+            .map(|path| dir.join(path).display().to_string())
+    };
+    match (image.kind.as_deref(), &image.path) {
+        (Some("cube"), Value::Array(faces)) if faces.len() == 6 => {
+            let faces = ["px", "nx", "py", "ny", "pz", "nz"]
+                .iter()
+                .zip(faces)
+                .map(|(face, value)| Some(format!("{face}={}", path(value)?)))
+                .collect::<Option<Vec<_>>>();
+            let Some(faces) = faces else {
+                declared
+                    .notes
+                    .push(format!("{name}: cube faces must be paths"));
+                return;
+            };
+            declared
+                .options
+                .push(format!("cubemap(name={name}, {})", faces.join(", ")));
+            declared.samplers.push((name.to_owned(), "Cube"));
+        }
+        (_, value) => {
+            let Some(path) = path(value) else {
+                declared
+                    .notes
+                    .push(format!("{name}: the imported path isn't supported"));
+                return;
+            };
+            declared
+                .options
+                .push(format!("texture(path={path}, name={name}, vflip=true)"));
+            declared.samplers.push((name.to_owned(), "2D"));
+        }
+    }
+}
+
+// ISF's fragCoord starts at the bottom left, `main` is renamed by the prelude
+const MAIN: &str = "\n#undef gl_FragCoord\n#undef main\nvoid main() {\n    \
+    shadey_frag_coord = vec4(gl_FragCoord.x, float(shadey_std.window_size.y) - gl_FragCoord.y, gl_FragCoord.zw);\n    \
+    isf_main();\n}\n";
+
+fn prelude(declared: &Declarations) -> String {
+    let mut prelude = format!(
+        "#version 450\n\
+        {}\
+        layout(set = 0, binding = 1) uniform ShadeyGui {{\n    float {TIME_OFFSET};\n{}}} shadey_gui;\n\
+        layout(location = 0) in vec2 shadey_texcoords;\n\
+        layout(location = 0) out vec4 shadey_frag_color;\n\
+        vec4 shadey_frag_coord;\n\
+        #define gl_FragCoord shadey_frag_coord\n\
+        #define gl_FragColor shadey_frag_color\n\
+        #define main isf_main\n\
+        #define isf_FragNormCoord shadey_texcoords\n\
+        #define vv_FragNormCoord shadey_texcoords\n\
+        #define RENDERSIZE vec2(shadey_std.window_size)\n\
+        #define TIME (shadey_std.time + shadey_gui.{TIME_OFFSET})\n\
+        #define TIMEDELTA shadey_std.delta_time\n\
+        #define FRAMEINDEX int(shadey_std.frame)\n\
+        #define DATE shadey_std.date\n\
+        #define PASSINDEX 0\n\
+        #define IMG_SIZE(image) vec2(textureSize(image, 0))\n\
+        #define IMG_NORM_PIXEL(image, coord) texture(image, coord)\n\
+        #define IMG_PIXEL(image, coord) texture(image, (coord) / IMG_SIZE(image))\n\
+        #define IMG_THIS_NORM_PIXEL(image) texture(image, isf_FragNormCoord)\n\
+        #define IMG_THIS_PIXEL(image) texture(image, isf_FragNormCoord)\n",
+        std_block(),
+        declared.members.concat(),
+    );
+    prelude += &declared.defines.concat();
+    // textures are bound in the order of their options, from group 1
+    for (i, (name, sampler)) in declared.samplers.iter().enumerate() {
+        prelude += &sampler_define(name, i + 1, sampler);
+    }
+    // after the declarations, as texture2D is a type too
+    prelude + "#define texture2D texture\n"
+}
+
+/// The WGSL shadey compiles for an ISF shader, and notes on what's lost.
+/// Error lines are those of the `.fs` file.
+pub fn to_wgsl(source: &str, shader_path: &Path) -> Result<(String, Vec<String>), Diagnostic> {
+    let error = |message: String| Diagnostic {
+        message,
+        line: None,
+    };
+    let json = header(source).ok_or_else(|| error("ISF JSON header wasn't found".to_owned()))?;
+    let header: Header = serde_json::from_str(json)
+        .map_err(|e| error(format!("ISF JSON header couldn't be read: {e}")))?;
+
+    let mut declared = Declarations {
+        slots: vec![format!("{TIME_OFFSET}: f32, // range(min=0,max=60)")],
+        members: Vec::new(),
+        defines: Vec::new(),
+        options: Vec::new(),
+        samplers: Vec::new(),
+        notes: Vec::new(),
+    };
+    let reserved = reserved_names();
+    let dir = shader_path.parent().unwrap_or(Path::new(""));
+    for input in &header.inputs {
+        declare_input(input, &reserved, dir, &mut declared);
+    }
+
+    match &header.imported {
+        Imported::Named(images) => {
+            for (name, image) in images {
+                declare_imported(name, image, dir, &mut declared);
+            }
+        }
+        Imported::Listed(images) => {
+            for image in images {
+                let Some(name) = &image.name else {
+                    declared
+                        .notes
+                        .push("An imported image has no NAME".to_owned());
+                    continue;
+                };
+                declare_imported(name, image, dir, &mut declared);
+            }
+        }
+    }
+
+    if header.passes.len() > 1 {
+        declared.notes.push(format!(
+            "Only the first of {} passes is rendered",
+            header.passes.len()
+        ));
+    }
+    for target in header.passes.iter().filter_map(|pass| pass.target.as_ref()) {
+        let still = shader_path.with_extension(format!("{target}.png"));
+        declared.notes.push(format!(
+            "{target}: the pass target is read as a still image from {still:?}"
+        ));
+        declared.options.push(format!(
+            "texture(path={}, name={target}, vflip=true)",
+            still.display()
+        ));
+        declared.samplers.push((target.clone(), "2D"));
+    }
+
+    let vertex_shader = shader_path.with_extension("vs");
+    if vertex_shader.exists() {
+        declared.notes.push(format!(
+            "The vertex shader {vertex_shader:?} isn't supported, the default one is used"
+        ));
+    }
+
+    // the JSON header is a comment, kept so lines match the file
+    let wgsl = translate(&prelude(&declared), source, MAIN)?;
+    let header = wgsl_header("ISF", &declared.slots, &declared.options);
+    Ok((header + &wgsl, declared.notes))
+}
+
+#[allow(unused)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        isf::{is_isf, to_wgsl},
+        parser,
+        shader::{compose_source, UniformKind},
+        wgsl::{RuntimeStruct, SlotValue},
+    };
+
+    const RINGS: &str = r#"/*{
+    "DESCRIPTION": "Rings around a point",
+    "ISFVSN": "2",
+    "INPUTS": [
+        {"NAME": "speed", "TYPE": "float", "DEFAULT": 0.5, "MIN": 0.0, "MAX": 2.0},
+        {"NAME": "tint", "TYPE": "color", "DEFAULT": [1.0, 0.5, 0.0, 1.0]},
+        {"NAME": "center", "TYPE": "point2D", "DEFAULT": [0.5, 0.5], "MIN": [0.0, 0.0], "MAX": [1.0, 1.0]},
+        {"NAME": "invert", "TYPE": "bool", "DEFAULT": true},
+        {"NAME": "count", "TYPE": "long", "VALUES": [2, 4, 8], "LABELS": ["2", "4", "8"], "DEFAULT": 4},
+        {"NAME": "inputImage", "TYPE": "image"},
+        {"NAME": "level", "TYPE": "audio"}
+    ]
+}*/
+
+void main() {
+    float d = distance(isf_FragNormCoord, center) * float(count);
+    float ring = fract(d - TIME * speed);
+    vec4 color = tint * ring + IMG_THIS_NORM_PIXEL(inputImage) * 0.1;
+    gl_FragColor = invert ? vec4(1.0) - color : color;
+    gl_FragColor.x += gl_FragCoord.y / RENDERSIZE.y * 0.0;
+}
+"#;
+
+    #[test]
+    fn isf_extension() {
+        assert!(is_isf(Path::new("shader/rings.fs")));
+        assert!(!is_isf(Path::new("shader/plasma.glsl")));
+    }
+
+    #[test]
+    fn inputs_become_slots() {
+        let (wgsl, notes) = to_wgsl(RINGS, Path::new("shader/rings.fs")).unwrap();
+        let (_, slots) =
+            parser::parse_struct_named(&wgsl, UniformKind::GuiControlled.into()).unwrap();
+        let gui_struct = RuntimeStruct::new(slots);
+        let value = |name| gui_struct.slot_value(gui_struct.get_slot_number(name).unwrap());
+
+        assert!(value("speed") == Some(SlotValue::Scalar(0.5)));
+        assert!(value("tint") == Some(SlotValue::Vector(vec![1.0, 0.5, 0.0, 1.0])));
+        assert!(value("center") == Some(SlotValue::Vector(vec![0.5, 0.5])));
+        assert!(value("invert") == Some(SlotValue::Scalar(1.0)));
+        assert!(gui_struct.slots[1].range() == Some(0.0..=2.0));
+        assert!(gui_struct.slots[5].range() == Some(2.0..=8.0));
+        assert!(
+            wgsl.contains("// texture(path=shader/inputImage.png, name=inputImage, vflip=true)\n")
+        );
+        assert!(notes.iter().any(|note| note.starts_with("level: audio")));
+    }
+
+    #[test]
+    fn translated_shader_composes() {
+        let (wgsl, _) = to_wgsl(RINGS, Path::new("shader/rings.fs")).unwrap();
+        let (shader, _) = compose_source(&wgsl).unwrap();
+        assert!(shader.check().is_ok());
+    }
+
+    #[test]
+    fn errors_located_in_fs() {
+        let source = "/*{\n    \"INPUTS\": []\n}*/\nvoid main() {\n    gl_FragColor = oops;\n}\n";
+        let diagnostic = to_wgsl(source, Path::new("bad.fs")).unwrap_err();
+        assert!(diagnostic.line == Some(5));
+    }
+}
@@ -0,0 +1,215 @@
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+use super::inputs::{EventContext, FrameContext, InputProvider};
+
+/// Keys are indexed by their javascript key code, like Shadertoy's keyboard input
+pub const KEY_COUNT: u32 = 256;
+const HELD_ROW: usize = 0;
+const PRESSED_ROW: usize = 1;
+const TOGGLED_ROW: usize = 2;
+
+/// Backs the 256x3 keyboard texture of the std lib: held keys, keys pressed
+/// this frame and toggled keys, one row each.
+pub struct Keyboard {
+    state: [u8; 3 * KEY_COUNT as usize],
+    texture: wgpu::Texture,
+}
+
+impl Keyboard {
+    /// The view is bound by the app while the provider keeps the texture to write to
+    pub fn new(device: &wgpu::Device) -> (Self, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("keyboard"),
+            size: wgpu::Extent3d {
+                width: KEY_COUNT,
+                height: 3,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[wgpu::TextureFormat::R8Unorm],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let keyboard = Self {
+            state: [0; 3 * KEY_COUNT as usize],
+            texture,
+        };
+
+        (keyboard, view)
+    }
+
+    /// Presses are dropped when `ignore_presses` (egui has the focus) but
+    /// releases always go through so no key stays stuck.
+    fn handle_input(&mut self, input: &KeyboardInput, ignore_presses: bool) {
+        let Some(code) = input.virtual_keycode.and_then(js_key_code) else {
+            return;
+        };
+        let code = code as usize;
+        let held = self.row(HELD_ROW)[code] != 0;
+
+        match input.state {
+            ElementState::Pressed if !ignore_presses && !held => {
+                self.row(HELD_ROW)[code] = u8::MAX;
+                self.row(PRESSED_ROW)[code] = u8::MAX;
+                self.row(TOGGLED_ROW)[code] ^= u8::MAX;
+            }
+            ElementState::Released => self.row(HELD_ROW)[code] = 0,
+            _ => {}
+        }
+    }
+
+    fn row(&mut self, row: usize) -> &mut [u8] {
+        let width = KEY_COUNT as usize;
+        &mut self.state[row * width..(row + 1) * width]
+    }
+}
+
+impl InputProvider for Keyboard {
+    fn fields(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    fn field_bytes(&self, _field: &str) -> Option<&[u8]> {
+        None
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent, ctx: &EventContext) -> bool {
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            self.handle_input(input, ctx.egui_wants_keyboard);
+        }
+        false // escape still has to reach handle_window_event
+    }
+
+    fn tick(&mut self, ctx: &mut FrameContext) {
+        ctx.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &self.state,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(KEY_COUNT),
+                rows_per_image: Some(3),
+            },
+            wgpu::Extent3d {
+                width: KEY_COUNT,
+                height: 3,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    fn end_frame(&mut self) {
+        self.row(PRESSED_ROW).fill(0);
+    }
+}
+
+fn js_key_code(key: VirtualKeyCode) -> Option<u8> {
+    use VirtualKeyCode::*;
+    let code = match key {
+        Back => 8,
+        Tab => 9,
+        Return | NumpadEnter => 13,
+        LShift | RShift => 16,
+        LControl | RControl => 17,
+        LAlt | RAlt => 18,
+        Pause => 19,
+        Capital => 20,
+        Escape => 27,
+        Space => 32,
+        PageUp => 33,
+        PageDown => 34,
+        End => 35,
+        Home => 36,
+        Left => 37,
+        Up => 38,
+        Right => 39,
+        Down => 40,
+        Insert => 45,
+        Delete => 46,
+        Key0 => 48,
+        Key1 => 49,
+        Key2 => 50,
+        Key3 => 51,
+        Key4 => 52,
+        Key5 => 53,
+        Key6 => 54,
+        Key7 => 55,
+        Key8 => 56,
+        Key9 => 57,
+        A => 65,
+        B => 66,
+        C => 67,
+        D => 68,
+        E => 69,
+        F => 70,
+        G => 71,
+        H => 72,
+        I => 73,
+        J => 74,
+        K => 75,
+        L => 76,
+        M => 77,
+        N => 78,
+        O => 79,
+        P => 80,
+        Q => 81,
+        R => 82,
+        S => 83,
+        T => 84,
+        U => 85,
+        V => 86,
+        W => 87,
+        X => 88,
+        Y => 89,
+        Z => 90,
+        Numpad0 => 96,
+        Numpad1 => 97,
+        Numpad2 => 98,
+        Numpad3 => 99,
+        Numpad4 => 100,
+        Numpad5 => 101,
+        Numpad6 => 102,
+        Numpad7 => 103,
+        Numpad8 => 104,
+        Numpad9 => 105,
+        NumpadMultiply => 106,
+        NumpadAdd => 107,
+        NumpadSubtract => 109,
+        NumpadDecimal => 110,
+        NumpadDivide => 111,
+        F1 => 112,
+        F2 => 113,
+        F3 => 114,
+        F4 => 115,
+        F5 => 116,
+        F6 => 117,
+        F7 => 118,
+        F8 => 119,
+        F9 => 120,
+        F10 => 121,
+        F11 => 122,
+        F12 => 123,
+        Semicolon => 186,
+        Equals => 187,
+        Comma => 188,
+        Minus => 189,
+        Period => 190,
+        Slash => 191,
+        Grave => 192,
+        LBracket => 219,
+        Backslash => 220,
+        RBracket => 221,
+        Apostrophe => 222,
+        _ => return None,
+    };
+
+    Some(code)
+}
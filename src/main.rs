@@ -12,18 +12,79 @@
 // 8. Gracefully handle bad app states
 
 mod app;
+mod audio;
+mod bindings;
+mod cli;
+mod editor;
+mod explore;
+mod expr;
+mod freeze;
+mod history;
+mod inputs;
+mod isf;
+mod keyboard;
+mod morph;
 mod parser;
+mod presets;
 mod shader;
+mod shadertoy;
+mod shadertoy_json;
+mod sweep;
+mod targets;
 mod texture;
+mod timeline;
+mod transport;
 mod ui;
+mod web;
 mod wgsl;
 
 use app::App;
+use cli::Cli;
+use inputs::Replay;
 use winit::{event::Event, event_loop::{ControlFlow, EventLoopBuilder}};
 
 fn main() {
+    let cli = Cli::parse();
+    if let Some(shader_path) = &cli.emit_wgsl {
+        if let Err(e) = app::emit_wgsl(shader_path) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(export_path) = &cli.import_shadertoy {
+        match shadertoy_json::import(export_path) {
+            Ok(import) => import.report(),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(shader_path) = &cli.bindings {
+        let name = cli.bindings_struct.as_deref().unwrap_or("GuiControlled");
+        match bindings::export(shader_path, name) {
+            Ok(paths) => paths.iter().for_each(|path| eprintln!("Wrote {path:?}")),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let replay = match cli.replay.as_deref().map(Replay::from_file).transpose() {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
     let event_loop = EventLoopBuilder::with_user_event().build();
-    let mut app = pollster::block_on(App::new(&event_loop));
+    let mut app = pollster::block_on(App::new(&event_loop, &cli));
+    if let Some(replay) = replay {
+        app.register_input(Box::new(replay));
+    }
 
     let event_loop_proxy = event_loop.create_proxy();
     event_loop.run(move |event, _, control_flow| {
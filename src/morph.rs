@@ -0,0 +1,209 @@
+use super::wgsl::{PType, RuntimeStruct, SlotValue, SlotValues, TType};
+
+/// One end of the crossfader
+pub struct Snapshot {
+    pub label: String,
+    pub values: SlotValues,
+}
+
+/// Crossfades the `GuiControlled` values between two snapshots, by hand or over time
+pub struct Morph {
+    pub a: Option<Snapshot>,
+    pub b: Option<Snapshot>,
+    pub mix: f32,
+    pub duration: f32,
+    // mix goes from .0 to .1 during the transition
+    transition: Option<(f32, f32)>,
+    elapsed: f32,
+}
+
+impl Default for Morph {
+    fn default() -> Self {
+        Self {
+            a: None,
+            b: None,
+            mix: 0.0,
+            duration: 2.0,
+            transition: None,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl Morph {
+    pub fn is_ready(&self) -> bool {
+        self.a.is_some() && self.b.is_some()
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Smoothly move the crossfader to `target`
+    pub fn transition_to(&mut self, target: f32) {
+        self.transition = Some((self.mix, target));
+        self.elapsed = 0.0;
+    }
+
+    pub fn stop(&mut self) {
+        self.transition = None;
+    }
+
+    /// Advances a running transition, returns true when the mix moved
+    pub fn tick(&mut self, delta: f32) -> bool {
+        let Some((from, to)) = self.transition else {
+            return false;
+        };
+        self.elapsed += delta;
+        let progress = (self.elapsed / self.duration.max(f32::EPSILON)).min(1.0);
+        let eased = progress * progress * (3.0 - 2.0 * progress);
+        self.mix = from + (to - from) * eased;
+        if progress >= 1.0 {
+            self.transition = None;
+        }
+        true
+    }
+
+    /// Write the values at the current mix, slots missing from a snapshot keep their value
+    pub fn apply(&self, gui_struct: &mut RuntimeStruct) {
+        let (Some(a), Some(b)) = (&self.a, &self.b) else {
+            return;
+        };
+
+        for slot in 1..=gui_struct.slots.len() {
+            let name = &gui_struct.slots[slot - 1].identifier;
+            let (Some(from), Some(to)) = (a.values.get(name), b.values.get(name)) else {
+                continue;
+            };
+            if let Some(value) = interpolate(&gui_struct.slots[slot - 1].typed, from, to, self.mix)
+            {
+                gui_struct.set_slot_value(slot, &value);
+            }
+        }
+    }
+}
+
+/// The vectors the ui edits as colors are blended in Oklab, integers are
+/// rounded when written back
+pub fn interpolate(typed: &TType, from: &SlotValue, to: &SlotValue, t: f32) -> Option<SlotValue> {
+    let (from, to) = (from.components(), to.components());
+    if from.len() != to.len() {
+        return None;
+    }
+    let t = t as f64;
+    let lerp = |a: f64, b: f64| a + (b - a) * t;
+
+    let color_scale = match typed {
+        TType::Vector(3 | 4, PType::F32) => Some(1.0),
+        TType::Vector(3 | 4, PType::U32) => Some(255.0),
+        _ => None,
+    };
+    let components = match color_scale {
+        Some(scale) => {
+            let rgb = |c: &[f64]| [c[0] / scale, c[1] / scale, c[2] / scale];
+            let (lab_a, lab_b) = (
+                linear_srgb_to_oklab(rgb(from)),
+                linear_srgb_to_oklab(rgb(to)),
+            );
+            let lab = [0, 1, 2].map(|i| lerp(lab_a[i], lab_b[i]));
+            let mut blended = oklab_to_linear_srgb(lab)
+                .map(|x| x.max(0.0) * scale)
+                .to_vec();
+            if from.len() == 4 {
+                blended.push(lerp(from[3], to[3]));
+            }
+            blended
+        }
+        None => from.iter().zip(to).map(|(a, b)| lerp(*a, *b)).collect(),
+    };
+
+    Some(match typed {
+        TType::Scalar(_) => SlotValue::Scalar(components[0]),
+        _ => SlotValue::Vector(components),
+    })
+}
+
+// https://bottosson.github.io/posts/oklab/
+fn linear_srgb_to_oklab([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+fn oklab_to_linear_srgb([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+
+    [
+        4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_,
+        -1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_,
+        -0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_,
+    ]
+}
+
+#[allow(unused)]
+mod tests {
+    use crate::{
+        morph::{interpolate, linear_srgb_to_oklab, oklab_to_linear_srgb, Morph},
+        wgsl::{PType::*, SlotValue, TType::*},
+    };
+
+    #[test]
+    fn oklab_roundtrip() {
+        let rgb = [0.8, 0.1, 0.35];
+        let back = oklab_to_linear_srgb(linear_srgb_to_oklab(rgb));
+        assert!(rgb.iter().zip(back).all(|(a, b)| (a - b).abs() < 1e-6));
+    }
+
+    #[test]
+    fn colors_keep_their_ends() {
+        let red = SlotValue::Vector(vec![1.0, 0.0, 0.0, 0.5]);
+        let blue = SlotValue::Vector(vec![0.0, 0.0, 1.0, 1.0]);
+
+        let Some(SlotValue::Vector(end)) = interpolate(&Vector(4, F32), &red, &blue, 1.0) else {
+            panic!()
+        };
+        assert!(end
+            .iter()
+            .zip([0.0, 0.0, 1.0, 1.0])
+            .all(|(a, b)| (a - b).abs() < 1e-6));
+
+        let Some(SlotValue::Vector(mid)) = interpolate(&Vector(4, F32), &red, &blue, 0.5) else {
+            panic!()
+        };
+        assert!((mid[3] - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn scalars_are_linear() {
+        let mid = interpolate(
+            &Scalar(F32),
+            &SlotValue::Scalar(2.0),
+            &SlotValue::Scalar(4.0),
+            0.25,
+        );
+        assert!(mid == Some(SlotValue::Scalar(2.5)));
+    }
+
+    #[test]
+    fn transition_reaches_target() {
+        let mut morph = Morph {
+            duration: 1.0,
+            ..Default::default()
+        };
+        morph.transition_to(1.0);
+        for _ in 0..10 {
+            morph.tick(0.11);
+        }
+
+        assert!(morph.mix == 1.0);
+        assert!(!morph.is_transitioning());
+    }
+}
@@ -7,7 +7,7 @@ use nom::{
     branch::alt,
     bytes::complete::{tag, take, take_until, take_while},
     character::{
-        complete::{alpha1, alphanumeric1, char, crlf, multispace0, not_line_ending},
+        complete::{alpha1, alphanumeric1, char, line_ending, multispace0, not_line_ending},
         is_digit,
     },
     combinator::{recognize, success},
@@ -18,9 +18,9 @@ use nom::{
     IResult,
 };
 
-use super::wgsl::{PType, StructSlot, StructSlotOptions, TType};
+use super::wgsl::{PType, SlotValue, StructSlot, StructSlotOptions, TType};
 
-fn ws<'a, F: 'a, O, E: ParseError<&'a str>>(
+pub fn ws<'a, F: 'a, O, E: ParseError<&'a str>>(
     inner: F,
 ) -> impl FnMut(&'a str) -> IResult<&'a str, O, E>
 where
@@ -29,7 +29,7 @@ where
     delimited(multispace0, inner, multispace0)
 }
 
-fn identifier(input: &str) -> IResult<&str, &str> {
+pub fn identifier(input: &str) -> IResult<&str, &str> {
     recognize(pair(
         alt((alpha1, tag("_"))),
         many0_count(alt((alphanumeric1, tag("_")))),
@@ -114,13 +114,17 @@ fn typer(input: &str) -> IResult<&str, TType> {
 }
 
 fn any_comment(input: &str) -> IResult<&str, &str> {
-    delimited(ws(tag("//")), not_line_ending, crlf)(input)
+    delimited(ws(tag("//")), not_line_ending, line_ending)(input)
 }
 
 fn struct_slot(input: &str) -> IResult<&str, StructSlot> {
     let (rest, (identifier, typed)) = separated_pair(ws(identifier), tag(":"), ws(typer))(input)?;
     let (rest, comment) = alt((any_comment, success("")))(rest)?;
     let options = structslot_option(comment).map(|(_, opt)| opt).ok();
+    let default = comment
+        .find("default(")
+        .and_then(|start| slot_default(&comment[start..]).ok())
+        .map(|(_, value)| value);
 
     Ok((
         rest,
@@ -128,6 +132,7 @@ fn struct_slot(input: &str) -> IResult<&str, StructSlot> {
             identifier: identifier.to_owned(),
             typed,
             options,
+            default,
         },
     ))
 }
@@ -165,7 +170,7 @@ pub fn adjustment_for_safe_insert(file_content: &str, name: &str) -> Option<usiz
     Some(r1.len() + input.len() - rest.len() + 3)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ShaderOptions {
     Texture {
         path: PathBuf,
@@ -173,10 +178,82 @@ pub enum ShaderOptions {
         u_addr_mode: Option<wgpu::AddressMode>,
         v_addr_mode: Option<wgpu::AddressMode>,
         w_addr_mode: Option<wgpu::AddressMode>,
+        /// Playback rate of animated textures (gifs and `*` sequences)
+        fps: Option<f32>,
+        /// Upload bottom row first, like Shadertoy's channel setting of the same name
+        vflip: bool,
+    },
+    Cubemap {
+        name: String,
+        source: CubemapSource,
+    },
+    Volume {
+        path: PathBuf,
+        name: String,
+        size: Option<[u32; 3]>,
+        format: VolumeFormat,
+        u_addr_mode: Option<wgpu::AddressMode>,
+        v_addr_mode: Option<wgpu::AddressMode>,
+        w_addr_mode: Option<wgpu::AddressMode>,
+    },
+    Audio {
+        path: PathBuf,
+        name: String,
     },
     Something,
 }
 
+/// Where the six faces of a cubemap come from. `Path` is either a directory
+/// holding one image per face, or a single equirect/cross/strip image.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CubemapSource {
+    Path(PathBuf),
+    Faces([PathBuf; 6]),
+}
+
+/// Texel layout of a volume source. 32 bit float sources are narrowed to
+/// 16 bit on upload so the volume stays filterable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeFormat {
+    R8,
+    Rgba8,
+    R16F,
+    Rgba16F,
+    R32F,
+    Rgba32F,
+}
+
+impl VolumeFormat {
+    pub fn channels(&self) -> usize {
+        use VolumeFormat::*;
+        match self {
+            R8 | R16F | R32F => 1,
+            Rgba8 | Rgba16F | Rgba32F => 4,
+        }
+    }
+
+    /// Size in bytes of one texel in the source data
+    pub fn texel_size(&self) -> usize {
+        use VolumeFormat::*;
+        self.channels()
+            * match self {
+                R8 | Rgba8 => 1,
+                R16F | Rgba16F => 2,
+                R32F | Rgba32F => 4,
+            }
+    }
+
+    pub fn gpu_format(&self) -> wgpu::TextureFormat {
+        use VolumeFormat::*;
+        match self {
+            R8 => wgpu::TextureFormat::R8Unorm,
+            Rgba8 => wgpu::TextureFormat::Rgba8Unorm,
+            R16F | R32F => wgpu::TextureFormat::R16Float,
+            Rgba16F | Rgba32F => wgpu::TextureFormat::Rgba16Float,
+        }
+    }
+}
+
 impl ShaderOptions {
     pub fn texture(path: &Path, name: &String) -> ShaderOptions {
         ShaderOptions::Texture {
@@ -185,6 +262,18 @@ impl ShaderOptions {
             u_addr_mode: None,
             v_addr_mode: None,
             w_addr_mode: None,
+            fps: None,
+            vflip: false,
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            ShaderOptions::Texture { name, .. }
+            | ShaderOptions::Cubemap { name, .. }
+            | ShaderOptions::Volume { name, .. }
+            | ShaderOptions::Audio { name, .. } => Some(name),
+            ShaderOptions::Something => None,
         }
     }
 }
@@ -234,6 +323,26 @@ fn address_mode(input: &str) -> Option<wgpu::AddressMode> {
     }
 }
 
+fn volume_format(input: &str) -> Option<VolumeFormat> {
+    match input.to_lowercase().as_str() {
+        "r8" => VolumeFormat::R8.into(),
+        "rgba8" => VolumeFormat::Rgba8.into(),
+        "r16f" => VolumeFormat::R16F.into(),
+        "rgba16f" => VolumeFormat::Rgba16F.into(),
+        "r32f" => VolumeFormat::R32F.into(),
+        "rgba32f" => VolumeFormat::Rgba32F.into(),
+        _ => None,
+    }
+}
+
+fn size3d(input: &str) -> Option<[u32; 3]> {
+    let dims = input
+        .split('x')
+        .map(|d| d.trim().parse().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    dims.try_into().ok()
+}
+
 fn texture(opt: &str) -> IResult<&str, ShaderOptions> {
     let (rest, _) = tag("texture")(opt)?;
     let (rest, arguments) = arguments(rest)?;
@@ -249,6 +358,92 @@ fn texture(opt: &str) -> IResult<&str, ShaderOptions> {
             u_addr_mode: arguments.get("u_mode").and_then(|x| address_mode(x)),
             v_addr_mode: arguments.get("v_mode").and_then(|x| address_mode(x)),
             w_addr_mode: arguments.get("w_mode").and_then(|x| address_mode(x)),
+            fps: match arguments.get("fps") {
+                Some(fps) => Some(
+                    fps.parse::<f32>()
+                        .map_err(|_| nom_error("Fps couldn't be parsed"))?,
+                ),
+                None => None,
+            },
+            vflip: arguments.get("vflip") == Some(&"true"),
+        },
+    ))
+}
+
+fn cubemap(opt: &str) -> IResult<&str, ShaderOptions> {
+    let (rest, _) = tag("cubemap")(opt)?;
+    let (rest, arguments) = arguments(rest)?;
+
+    let name = arguments
+        .get("name")
+        .ok_or_else(|| nom_error(rest))?
+        .to_string();
+    let source = match arguments.get("path") {
+        Some(path) => CubemapSource::Path(path.into()),
+        None => {
+            // faces in wgpu layer order: +X, -X, +Y, -Y, +Z, -Z
+            let face = |key| {
+                arguments
+                    .get(key)
+                    .map(PathBuf::from)
+                    .ok_or_else(|| nom_error(rest))
+            };
+            CubemapSource::Faces([
+                face("px")?,
+                face("nx")?,
+                face("py")?,
+                face("ny")?,
+                face("pz")?,
+                face("nz")?,
+            ])
+        }
+    };
+
+    Ok((rest, ShaderOptions::Cubemap { name, source }))
+}
+
+fn volume(opt: &str) -> IResult<&str, ShaderOptions> {
+    let (rest, _) = tag("volume")(opt)?;
+    let (rest, arguments) = arguments(rest)?;
+
+    let size = match arguments.get("size") {
+        Some(size) => Some(size3d(size).ok_or_else(|| nom_error("Size couldn't be parsed"))?),
+        None => None,
+    };
+    let format = match arguments.get("format") {
+        Some(format) => volume_format(format).ok_or_else(|| nom_error("Unknown volume format"))?,
+        None => VolumeFormat::Rgba8,
+    };
+
+    Ok((
+        rest,
+        ShaderOptions::Volume {
+            path: arguments.get("path").ok_or_else(|| nom_error(rest))?.into(),
+            name: arguments
+                .get("name")
+                .ok_or_else(|| nom_error(rest))?
+                .to_string(),
+            size,
+            format,
+            u_addr_mode: arguments.get("u_mode").and_then(|x| address_mode(x)),
+            v_addr_mode: arguments.get("v_mode").and_then(|x| address_mode(x)),
+            w_addr_mode: arguments.get("w_mode").and_then(|x| address_mode(x)),
+        },
+    ))
+}
+
+fn audio(opt: &str) -> IResult<&str, ShaderOptions> {
+    let (rest, _) = tag("audio")(opt)?;
+    let (rest, arguments) = arguments(rest)?;
+
+    Ok((
+        rest,
+        ShaderOptions::Audio {
+            path: arguments.get("path").ok_or_else(|| nom_error(rest))?.into(),
+            name: arguments
+                .get("name")
+                .ok_or_else(|| nom_error(rest))?
+                .to_string(),
         },
     ))
 }
@@ -258,13 +453,13 @@ fn something(opt: &str) -> IResult<&str, ShaderOptions> {
 }
 
 pub fn shader_option(opt: &str) -> IResult<&str, ShaderOptions> {
-    alt((texture, something))(opt)
+    alt((texture, cubemap, volume, audio, something))(opt)
 }
 
 pub fn parse_options(file_content: &str) -> IResult<&str, Vec<ShaderOptions>> {
     let (input, _) = take_until("// Shadey")(file_content)?;
     let (rest, _) = tag("// Shadey")(input)?;
-    let any_comment1 = delimited(ws(tag("//")), shader_option, crlf);
+    let any_comment1 = delimited(ws(tag("//")), shader_option, line_ending);
 
     fold_many0(any_comment1, Vec::new, |mut acc: Vec<_>, item| {
         acc.push(item);
@@ -289,6 +484,198 @@ fn range(comment: &str) -> IResult<&str, StructSlotOptions> {
     Ok((rest2, StructSlotOptions::Slider { range: min..=max }))
 }
 
+fn expression(comment: &str) -> IResult<&str, StructSlotOptions> {
+    let (rest, _) = tag("expr")(comment)?;
+    let inner = rest.strip_prefix('(').ok_or_else(|| nom_error(rest))?;
+    // the expression can hold parentheses itself, it runs up to the one closing `expr(`
+    let mut depth = 0;
+    let end = inner
+        .char_indices()
+        .find_map(|(i, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 => return Some(i),
+                ')' => depth -= 1,
+                _ => (),
+            }
+            None
+        })
+        .ok_or_else(|| nom_error(rest))?;
+    let (source, rest) = (&inner[..end], &inner[end + 1..]);
+
+    Ok((
+        rest,
+        StructSlotOptions::Expression {
+            source: source.trim().to_owned(),
+        },
+    ))
+}
+
+/// `default(0.5)` or `default(1, 0, 0, 1)`, written after the slot's other option
+pub fn slot_default(comment: &str) -> IResult<&str, SlotValue> {
+    let (rest, _) = tag("default")(comment)?;
+    let (rest, list) = delimited(tag("("), take_until(")"), tag(")"))(rest)?;
+    let components = list
+        .split(',')
+        .map(|x| x.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| nom_error("Default couldn't be parsed"))?;
+
+    Ok((
+        rest,
+        match components[..] {
+            [x] => SlotValue::Scalar(x),
+            _ => SlotValue::Vector(components),
+        },
+    ))
+}
+
 pub fn structslot_option(comment: &str) -> IResult<&str, StructSlotOptions> {
-    range(comment)
+    alt((range, expression))(comment)
+}
+
+#[allow(unused)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        parser::{
+            parse_options, shader_option, slot_default, struct_slot, structslot_option,
+            CubemapSource, ShaderOptions, VolumeFormat,
+        },
+        wgsl::{SlotValue, StructSlotOptions},
+    };
+
+    #[test]
+    fn cubemap_from_path() {
+        let (_, opt) = shader_option("cubemap(name=sky, path=texture/sky/)").unwrap();
+
+        let ShaderOptions::Cubemap {
+            name,
+            source: CubemapSource::Path(path),
+        } = opt
+        else {
+            panic!("expected a cubemap with a path, got {opt:?}");
+        };
+        assert!(name == "sky");
+        assert!(path == Path::new("texture/sky/"));
+    }
+
+    #[test]
+    fn cubemap_from_faces() {
+        let (_, opt) = shader_option(
+            "cubemap(name=sky,px=a.png,nx=b.png,py=c.png,ny=d.png,pz=e.png,nz=f.png)",
+        )
+        .unwrap();
+
+        let ShaderOptions::Cubemap {
+            source: CubemapSource::Faces(faces),
+            ..
+        } = opt
+        else {
+            panic!("expected a cubemap with six faces, got {opt:?}");
+        };
+        assert!(faces[0] == Path::new("a.png"));
+        assert!(faces[5] == Path::new("f.png"));
+    }
+
+    #[test]
+    fn cubemap_missing_face() {
+        assert!(shader_option("cubemap(name=sky,px=a.png,nx=b.png)").is_err());
+    }
+
+    #[test]
+    fn volume_with_size_and_format() {
+        let (_, opt) = shader_option(
+            "volume(name=sdf, path=sdf.raw, size=64x32x16, format=r32f, w_mode=repeat)",
+        )
+        .unwrap();
+
+        let ShaderOptions::Volume {
+            size,
+            format,
+            w_addr_mode,
+            ..
+        } = opt
+        else {
+            panic!("expected a volume, got {opt:?}");
+        };
+        assert!(size == Some([64, 32, 16]));
+        assert!(format == VolumeFormat::R32F);
+        assert!(w_addr_mode == Some(wgpu::AddressMode::Repeat));
+    }
+
+    #[test]
+    fn volume_bad_size() {
+        assert!(shader_option("volume(name=sdf, path=sdf.raw, size=64x32)").is_err());
+    }
+
+    #[test]
+    fn texture_sequence_with_fps() {
+        let (_, opt) = shader_option("texture(path=frames/*.png, name=walk, fps=12)").unwrap();
+
+        let ShaderOptions::Texture { path, fps, .. } = opt else {
+            panic!("expected a texture, got {opt:?}");
+        };
+        assert!(path == Path::new("frames/*.png"));
+        assert!(fps == Some(12.0));
+    }
+
+    #[test]
+    fn slot_expression() {
+        let (_, opt) = structslot_option("expr(0.5 + 0.5*sin(time*2))").unwrap();
+        assert!(
+            opt == StructSlotOptions::Expression {
+                source: "0.5 + 0.5*sin(time*2)".to_owned()
+            }
+        );
+        assert!(structslot_option("expr sin(t)").is_err());
+    }
+
+    #[test]
+    fn options_with_either_line_ending() {
+        for newline in ["\n", "\r\n"] {
+            let source = [
+                "// Shadey",
+                "// texture(path=a.png, name=a)",
+                "// audio(path=b.wav, name=b)",
+                "",
+            ]
+            .join(newline);
+            let (_, options) = parse_options(&source).unwrap();
+            assert!(options.len() == 2);
+
+            let (_, slot) =
+                struct_slot(&format!("speed: f32, // range(min=0,max=4){newline}")).unwrap();
+            assert!(slot.range() == Some(0.0..=4.0));
+        }
+    }
+
+    #[test]
+    fn slot_with_range_and_default() {
+        let (_, slot) =
+            struct_slot("center: vec2<f32>, // range(min=0,max=1) default(0.5, 0.25)\r\n").unwrap();
+        assert!(slot.range() == Some(0.0..=1.0));
+        assert!(slot.default == Some(SlotValue::Vector(vec![0.5, 0.25])));
+
+        let (_, default) = slot_default("default(2)").unwrap();
+        assert!(default == SlotValue::Scalar(2.0));
+        assert!(slot_default("default(a, b)").is_err());
+    }
+
+    #[test]
+    fn slot_with_expression_and_default() {
+        let (_, slot) = struct_slot("level: f32, // expr(sin(t)) default(0.5)\r\n").unwrap();
+        assert!(
+            slot.options
+                == Some(StructSlotOptions::Expression {
+                    source: "sin(t)".to_owned()
+                })
+        );
+        assert!(slot.default == Some(SlotValue::Scalar(0.5)));
+
+        let (rest, _) = structslot_option("expr((1 + 2) * 3) and more").unwrap();
+        assert!(rest == " and more");
+        assert!(structslot_option("expr(sin(t)").is_err());
+    }
 }
@@ -0,0 +1,134 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::wgsl::{RuntimeStruct, SlotValues};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PresetFile {
+    #[serde(default)]
+    last_used: Option<String>,
+    #[serde(default)]
+    presets: BTreeMap<String, SlotValues>,
+}
+
+/// Named snapshots of the `GuiControlled` values, kept in a sidecar file next
+/// to the shader (`voronoi.wgsl` -> `voronoi.presets.json`)
+pub struct Presets {
+    path: PathBuf,
+    file: PresetFile,
+    pub new_name: String,
+}
+
+impl Presets {
+    pub fn sidecar_path(shader_path: &Path) -> PathBuf {
+        shader_path.with_extension("presets.json")
+    }
+
+    /// Starts empty when the sidecar is missing or unreadable
+    pub fn for_shader(shader_path: &Path) -> Self {
+        let path = Self::sidecar_path(shader_path);
+        let file = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                eprintln!("Presets file {path:?} couldn't be parsed: {e}");
+                PresetFile::default()
+            }),
+            Err(_) => PresetFile::default(),
+        };
+
+        Self {
+            path,
+            file,
+            new_name: String::new(),
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.file.presets.keys().map(String::as_str)
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.file.last_used.as_deref()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SlotValues> {
+        self.file.presets.get(name)
+    }
+
+    /// Load the last used preset, done when a shader is opened
+    pub fn apply_last_used(&self, gui_struct: &mut RuntimeStruct) {
+        if let Some(values) = self.selected().and_then(|name| self.get(name)) {
+            gui_struct.apply_values(values);
+        }
+    }
+
+    pub fn select(&mut self, name: &str, gui_struct: &mut RuntimeStruct) {
+        let Some(values) = self.file.presets.get(name) else {
+            return;
+        };
+        gui_struct.apply_values(values);
+        self.file.last_used = Some(name.to_owned());
+        self.write();
+    }
+
+    /// Overwrites a preset of the same name
+    pub fn save(&mut self, name: &str, gui_struct: &RuntimeStruct) {
+        self.file
+            .presets
+            .insert(name.to_owned(), gui_struct.values());
+        self.file.last_used = Some(name.to_owned());
+        self.write();
+    }
+
+    pub fn delete(&mut self, name: &str) {
+        if self.file.presets.remove(name).is_none() {
+            return;
+        }
+        if self.selected() == Some(name) {
+            self.file.last_used = None;
+        }
+        self.write();
+    }
+
+    fn write(&self) {
+        let content = serde_json::to_string_pretty(&self.file).expect("Presets are serializable");
+        if let Err(e) = std::fs::write(&self.path, content) {
+            eprintln!("Presets couldn't be saved to {:?}: {e}", self.path);
+        }
+    }
+}
+
+#[allow(unused)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        presets::{PresetFile, Presets},
+        wgsl::SlotValue,
+    };
+
+    #[test]
+    fn sidecar_next_to_shader() {
+        let path = Presets::sidecar_path(Path::new("shader/voronoi.wgsl"));
+        assert!(path == Path::new("shader/voronoi.presets.json"));
+    }
+
+    #[test]
+    fn preset_file_format() {
+        let file: PresetFile = serde_json::from_str(
+            r#"{
+                "last_used": "calm",
+                "presets": { "calm": { "speed": 0.2, "tint": [1.0, 0.5, 0.0] } }
+            }"#,
+        )
+        .unwrap();
+
+        let calm = &file.presets["calm"];
+        assert!(file.last_used.as_deref() == Some("calm"));
+        assert!(calm["speed"] == SlotValue::Scalar(0.2));
+        assert!(calm["tint"] == SlotValue::Vector(vec![1.0, 0.5, 0.0]));
+    }
+}
@@ -1,46 +1,134 @@
-use std::path::Path;
+use std::{ops::Range, path::Path};
 
 use wgpu::util::DeviceExt;
 
 use super::{parser, texture, wgsl::RuntimeStruct};
 
-const AUTOGEN_DELIM: &str = "// ==================== AUTOGENERATED ====================";
+pub const AUTOGEN_DELIM: &str = "// ==================== AUTOGENERATED ====================";
 pub const GUICONTROLLED_DEF: &str = "@group(0) @binding(1)\n\
 var<uniform> _gui: GuiControlled;";
 
 pub struct ShaderFileBuilder {
     content: String,
+    options: Vec<texture::TextureOption>,
     inject_pos: usize,
+    user_start: usize,
+    autogen_start: usize,
 }
 
-pub struct ShaderFileBuf(String);
+/// The composed source: std lib, then the user's shader with the autogen
+/// section injected right after its `GuiControlled` struct
+pub struct ShaderFileBuf {
+    content: String,
+    user_start: usize,
+    autogen: Range<usize>,
+}
 
 impl AsRef<str> for ShaderFileBuf {
     fn as_ref(&self) -> &str {
-        &self.0
+        &self.content
+    }
+}
+
+/// A compile error, with its line in the user's shader when it comes from there
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+impl ShaderFileBuf {
+    /// The user's shader as it was before injection
+    pub fn user_source(&self) -> String {
+        let Range { start, end } = self.autogen;
+        self.content[self.user_start..start].to_owned() + &self.content[end..]
+    }
+
+    /// Where a byte of the composed source comes from in the user's shader,
+    /// None for the std lib and the autogen section
+    pub fn user_offset(&self, offset: usize) -> Option<usize> {
+        if offset < self.user_start || self.autogen.contains(&offset) {
+            return None;
+        }
+        let autogen_len = if offset >= self.autogen.end {
+            self.autogen.len()
+        } else {
+            0
+        };
+        Some(offset - self.user_start - autogen_len)
+    }
+
+    /// The std lib, the user's shader and the autogen section, in order, by name
+    pub fn segments(&self) -> [(&'static str, Range<usize>); 4] {
+        [
+            ("std", 0..self.user_start),
+            ("shader", self.user_start..self.autogen.start),
+            ("autogen", self.autogen.clone()),
+            ("shader", self.autogen.end..self.content.len()),
+        ]
+    }
+
+    /// Parse and validate with naga, to locate errors before wgpu sees the shader
+    pub fn check(&self) -> Result<(), Diagnostic> {
+        self.module().map(|_| ())
+    }
+
+    /// The validated naga module, as handed to its back-ends
+    pub fn module(&self) -> Result<(naga::Module, naga::valid::ModuleInfo), Diagnostic> {
+        let source = self.as_ref();
+        let diagnostic = |message: String, location: Option<naga::SourceLocation>| {
+            let line = location
+                .and_then(|l| self.user_offset(l.offset as usize))
+                .map(|offset| self.user_source()[..offset].matches('\n').count() + 1);
+            Diagnostic { message, line }
+        };
+
+        let module = naga::front::wgsl::parse_str(source)
+            .map_err(|e| diagnostic(e.emit_to_string(source), e.location(source)))?;
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .map_err(|e| diagnostic(e.emit_to_string(source), e.location(source)))?;
+
+        Ok((module, info))
     }
 }
 
 impl ShaderFileBuilder {
     pub fn new(shader_path: &Path) -> Option<Self> {
+        let shader_content = std::fs::read_to_string(shader_path).ok()?;
+        Self::from_source(&shader_content)
+    }
+
+    /// Build from unsaved content, e.g. the editor's buffer
+    pub fn from_source(shader_content: &str) -> Option<Self> {
         let std_content =
             std::fs::read_to_string("shader/std.wgsl").expect("Shader std lib couldn't be found");
-        let shader_content = std::fs::read_to_string(shader_path).ok()?;
+        let user_start = std_content.len();
 
         let candidate_inject_pos = std_content.len()
             + parser::adjustment_for_safe_insert(
-                &shader_content,
+                shader_content,
                 UniformKind::GuiControlled.into(),
             )?;
-        let content = std_content + &shader_content[..];
+        let content = std_content + shader_content;
 
         if candidate_inject_pos > content.len() {
             return None;
         }
 
+        let (_, options) = parser::parse_options(&content).unwrap_or(("", Vec::new()));
         let mut out = Self {
+            options: options
+                .into_iter()
+                .map(texture::TextureOption::resolve)
+                .collect(),
             content,
             inject_pos: candidate_inject_pos,
+            user_start,
+            autogen_start: candidate_inject_pos,
         };
         out.inject_content(AUTOGEN_DELIM);
 
@@ -56,23 +144,80 @@ impl ShaderFileBuilder {
     pub fn build(mut self) -> ShaderFileBuf {
         self.inject_content(AUTOGEN_DELIM);
 
-        ShaderFileBuf(self.content)
+        ShaderFileBuf {
+            content: self.content,
+            user_start: self.user_start,
+            autogen: self.autogen_start..self.inject_pos,
+        }
     }
 
     pub fn uniform(&self, device: &wgpu::Device, kind: UniformKind) -> Uniform {
-        Uniform::new(device, self, kind)
+        Uniform::new(device, self.runtime_struct(kind), kind)
     }
 
+    /// The uniform struct of that kind, laid out as its buffer will be
+    pub fn runtime_struct(&self, kind: UniformKind) -> RuntimeStruct {
+        let uniform_typename = kind.into();
+        let (_, slots) = parser::parse_struct_named(&self.content, uniform_typename)
+            .unwrap_or_else(|_| panic!("Problem with typename {uniform_typename} in shader"));
+        RuntimeStruct::new(slots)
+    }
+
+    /// Inject the autogen section, no device needed: `_gui` with a getter per
+    /// slot, then the bindings of the texture options
+    pub fn compose(mut self) -> (ShaderFileBuf, RuntimeStruct) {
+        let gui_struct = self.runtime_struct(UniformKind::GuiControlled);
+        self.inject_content(GUICONTROLLED_DEF);
+        for slot in &gui_struct.slots {
+            self.inject_content(&slot.generate_definition());
+        }
+        for definition in self.texture_definitions() {
+            self.inject_content(&definition);
+        }
+        (self.build(), gui_struct)
+    }
+
+    /// The `// Shadey` options, resolved when the builder was made, none
+    /// when they don't parse
+    pub fn options(&self) -> &[texture::TextureOption] {
+        &self.options
+    }
+
+    /// One texture per texture option, a placeholder for those that fail to
+    /// load, so the bind groups line up with `texture_definitions`
     pub fn textures(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<texture::Texture> {
-        let (_, options) = parser::parse_options(&self.content).unwrap_or(("", Vec::new()));
+        self.options
+            .iter()
+            .filter_map(|opts| {
+                texture::Texture::from_shader_option(device, queue, opts).or_else(|| {
+                    let placeholder = texture::Texture::placeholder(device, queue, opts)?;
+                    eprintln!(
+                        "Texture {} couldn't be loaded, a transparent placeholder is bound",
+                        placeholder.name
+                    );
+                    Some(placeholder)
+                })
+            })
+            .collect()
+    }
 
-        options
+    /// The bindings and helpers of the texture options, bind group by bind group
+    pub fn texture_definitions(&self) -> Vec<String> {
+        self.options
             .iter()
-            .filter_map(|opts| texture::Texture::from_shader_option(device, queue, opts))
+            .filter(|opts| !matches!(opts.options, parser::ShaderOptions::Something))
+            .enumerate()
+            .filter_map(|(i, opts)| texture::Texture::generate_definition(opts, i))
             .collect()
     }
 }
 
+/// Compose a shader from its source alone, without a device
+pub fn compose_source(source: &str) -> Option<(ShaderFileBuf, RuntimeStruct)> {
+    ShaderFileBuilder::from_source(source).map(ShaderFileBuilder::compose)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UniformKind {
     StandardLib,
     GuiControlled,
@@ -93,12 +238,8 @@ pub struct Uniform {
 }
 
 impl Uniform {
-    fn new(device: &wgpu::Device, shader_content: &ShaderFileBuilder, kind: UniformKind) -> Self {
-        let uniform_typename = kind.into();
-        let (_, slots) = parser::parse_struct_named(&shader_content.content, uniform_typename)
-            .unwrap_or_else(|_| panic!("Problem with typename {uniform_typename} in shader"));
-
-        let runtime_struct = RuntimeStruct::new(slots);
+    pub fn new(device: &wgpu::Device, runtime_struct: RuntimeStruct, kind: UniformKind) -> Self {
+        let uniform_typename: &str = kind.into();
         let gpu_buffer_handle = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some(&format!("{uniform_typename} Buffer")),
             contents: runtime_struct.buffer(),
@@ -111,3 +252,30 @@ impl Uniform {
         }
     }
 }
+
+#[allow(unused)]
+mod tests {
+    use crate::shader::{compose_source, ShaderFileBuilder};
+
+    #[test]
+    fn errors_located_in_user_source() {
+        let source =
+            "struct GuiControlled {\n    speed: f32,\n};\n\nfn f() -> f32 {\n    return oops;\n}\n";
+        let (shader, _) = compose_source(source).unwrap();
+
+        assert!(shader.user_source() == source);
+        assert!(shader.check().unwrap_err().line == Some(6));
+    }
+
+    #[test]
+    fn texture_definitions_follow_options() {
+        let source = "struct GuiControlled {\r\n    speed: f32,\r\n};\r\n\r\n// Shadey\r\n// texture(path=missing.png, name=sm)\r\n// volume(name=fog, path=missing.raw, size=4x4x4)\r\n";
+        let builder = ShaderFileBuilder::from_source(source).unwrap();
+        let definitions = builder.texture_definitions();
+
+        assert!(definitions.len() == 2);
+        assert!(definitions[0].contains("@group(1) @binding(0)"));
+        assert!(definitions[0].contains("fn texture_sm("));
+        assert!(definitions[1].contains("var t_diffuse2: texture_3d<f32>;"));
+    }
+}
@@ -0,0 +1,309 @@
+//! Shadertoy-style GLSL, a `void mainImage(out vec4, in vec2)` with the
+//! `iTime`, `iResolution`, ... inputs, translated to a shadey WGSL shader
+//! through naga. The GLSL gets a prelude binding those inputs to `StdUniform`
+//! and the textures of the `// Shadey` options named `iChannel0` to `iChannel3`.
+//!
+//! Not supported: `iChannelResolution`, `iChannelTime`, animated and audio
+//! channels, multiple passes (exports are split by `shadertoy_json`).
+
+use std::{collections::HashSet, path::Path};
+
+use naga::{
+    front::glsl::{Frontend, Options},
+    valid::{Capabilities, ValidationFlags, Validator},
+    ShaderStage,
+};
+
+use super::{
+    parser::{self, ShaderOptions},
+    shader::{Diagnostic, UniformKind},
+    texture::animated_source,
+    wgsl::{PType, TType},
+};
+
+const CHANNELS: usize = 4;
+
+// the only GuiControlled slot of an imported shader, added to iTime
+pub const TIME_OFFSET: &str = "time_offset";
+
+pub fn is_shadertoy(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "glsl" || extension == "frag")
+}
+
+fn glsl_type(typed: &TType) -> Option<String> {
+    let prefix = |x: &PType| match x {
+        PType::F32 => Some(""),
+        PType::U32 => Some("u"),
+        PType::I32 => Some("i"),
+        _ => None,
+    };
+    match typed {
+        TType::Scalar(PType::F32) => Some("float".to_owned()),
+        TType::Scalar(PType::U32) => Some("uint".to_owned()),
+        TType::Scalar(PType::I32) => Some("int".to_owned()),
+        TType::Vector(n, x) => Some(format!("{}vec{n}", prefix(x)?)),
+        _ => None,
+    }
+}
+
+/// Group of each channel, the textures of the options are bound in order
+/// from group 1
+fn channel_groups(
+    options: &[ShaderOptions],
+) -> Result<[Option<(usize, &'static str)>; CHANNELS], String> {
+    let mut channels = [None; CHANNELS];
+    let textures = options
+        .iter()
+        .filter(|option| !matches!(option, ShaderOptions::Something));
+    for (i, option) in textures.enumerate() {
+        let (name, sampler) = match option {
+            ShaderOptions::Texture { name, path, .. } if animated_source(path).is_some() => {
+                (name, Err("animated textures"))
+            }
+            ShaderOptions::Texture { name, .. } => (name, Ok("2D")),
+            ShaderOptions::Cubemap { name, .. } => (name, Ok("Cube")),
+            ShaderOptions::Volume { name, .. } => (name, Ok("3D")),
+            ShaderOptions::Audio { name, .. } => (name, Err("audio")),
+            ShaderOptions::Something => continue,
+        };
+        let Some(channel) = name
+            .strip_prefix("iChannel")
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|n| *n < CHANNELS)
+        else {
+            continue;
+        };
+        let sampler =
+            sampler.map_err(|kind| format!("{name}: {kind} aren't supported as channels"))?;
+        channels[channel] = Some((i + 1, sampler));
+    }
+    Ok(channels)
+}
+
+/// The std lib uniform as a GLSL block, `shadey_std`
+pub fn std_block() -> String {
+    let std_content = std_lib();
+    let (_, std_slots) = parser::parse_struct_named(&std_content, UniformKind::StandardLib.into())
+        .expect("Std lib should declare its uniform");
+    let members = std_slots
+        .iter()
+        .filter_map(|slot| {
+            Some(format!(
+                "    {} {};\n",
+                glsl_type(&slot.typed)?,
+                slot.identifier
+            ))
+        })
+        .collect::<String>();
+    format!("layout(set = 0, binding = 0) uniform ShadeyStd {{\n{members}}} shadey_std;\n")
+}
+
+/// A texture bound at `group` by its option, sampled in GLSL as `name`
+pub fn sampler_define(name: &str, group: usize, sampler: &str) -> String {
+    format!(
+        "layout(set = {group}, binding = 0) uniform texture{sampler} shadey_{name}_texture;\n\
+        layout(set = {group}, binding = 1) uniform sampler shadey_{name}_sampler;\n\
+        #define {name} sampler{sampler}(shadey_{name}_texture, shadey_{name}_sampler)\n"
+    )
+}
+
+fn prelude(channels: &[Option<(usize, &str)>]) -> String {
+    let mut prelude = format!(
+        "#version 450\n\
+        {}\
+        layout(set = 0, binding = 1) uniform ShadeyGui {{\n    float {TIME_OFFSET};\n}} shadey_gui;\n\
+        layout(location = 0) in vec2 shadey_texcoords;\n\
+        layout(location = 0) out vec4 shadey_frag_color;\n\
+        #define iResolution vec3(vec2(shadey_std.window_size), 1.0)\n\
+        #define iTime (shadey_std.time + shadey_gui.{TIME_OFFSET})\n\
+        #define iTimeDelta shadey_std.delta_time\n\
+        #define iFrameRate (1.0 / shadey_std.delta_time)\n\
+        #define iFrame int(shadey_std.frame)\n\
+        #define iMouse shadey_std.mouse_click\n\
+        #define iDate (shadey_std.date - vec4(0.0, 1.0, 0.0, 0.0))\n\
+        #define iSampleRate 44100.0\n",
+        std_block()
+    );
+    for (channel, group) in channels.iter().enumerate() {
+        let Some((group, sampler)) = group else {
+            continue;
+        };
+        prelude += &sampler_define(&format!("iChannel{channel}"), *group, sampler);
+    }
+    prelude
+}
+
+// fragCoord starts at the bottom left in Shadertoy
+const MAIN: &str = "\nvoid main() {\n    \
+    mainImage(shadey_frag_color, vec2(gl_FragCoord.x, float(shadey_std.window_size.y) - gl_FragCoord.y));\n}\n";
+
+/// Names declared by the std lib and the autogen section, which the
+/// translated shader must not redeclare
+pub fn reserved_names() -> HashSet<String> {
+    let std_content = std_lib();
+    let declared = std_content.split_whitespace().collect::<Vec<_>>();
+    let mut names = declared
+        .windows(2)
+        .filter(|w| w[0] == "fn" || w[0] == "struct" || w[0].starts_with("var"))
+        .map(|w| {
+            w[1].split(['(', ':', '<', '{'])
+                .next()
+                .unwrap_or_default()
+                .to_owned()
+        })
+        .collect::<HashSet<_>>();
+    names.extend(["_gui", TIME_OFFSET, UniformKind::GuiControlled.into()].map(str::to_owned));
+    names
+}
+
+fn std_lib() -> String {
+    std::fs::read_to_string("shader/std.wgsl").expect("Shader std lib couldn't be found")
+}
+
+/// WGSL of a GLSL fragment shader whose entry point is `main`, renamed to
+/// `fs_main`. Error lines are counted from the start of `glsl`.
+pub fn translate(prelude: &str, glsl: &str, main: &str) -> Result<String, Diagnostic> {
+    let error = |message: String| Diagnostic {
+        message,
+        line: None,
+    };
+    let source = format!("{prelude}{glsl}{main}");
+
+    let mut module = Frontend::default()
+        .parse(&Options::from(ShaderStage::Fragment), &source)
+        .map_err(|errors| {
+            let first = errors.first();
+            Diagnostic {
+                message: errors
+                    .iter()
+                    .map(|e| e.kind.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                line: first
+                    .map(|e| e.meta.location(&source).line_number as usize)
+                    .and_then(|line| line.checked_sub(prelude.lines().count())),
+            }
+        })?;
+
+    module.entry_points[0].name = "fs_main".to_owned();
+    let reserved = reserved_names();
+    let rename = |name: &mut Option<String>| {
+        if let Some(name) = name
+            .as_mut()
+            .filter(|name| reserved.contains(name.as_str()))
+        {
+            name.push_str("_st");
+        }
+    };
+    for (_, function) in module.functions.iter_mut() {
+        rename(&mut function.name);
+    }
+    for (_, global) in module.global_variables.iter_mut() {
+        rename(&mut global.name);
+    }
+    let types = module
+        .types
+        .iter()
+        .map(|(handle, _)| handle)
+        .collect::<Vec<_>>();
+    for handle in types {
+        let mut typed = module.types[handle].clone();
+        rename(&mut typed.name);
+        if typed != module.types[handle] {
+            module.types.replace(handle, typed);
+        }
+    }
+
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|e| error(e.emit_to_string(&source)))?;
+    naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::empty())
+        .map_err(|e| error(e.to_string()))
+}
+
+/// What precedes translated WGSL: the `GuiControlled` struct shadey parses,
+/// then the `// Shadey` options
+pub fn wgsl_header(origin: &str, slots: &[String], options: &[String]) -> String {
+    let gui_struct: &str = UniformKind::GuiControlled.into();
+    let mut header = vec![
+        format!("// Translated from {origin}, edit the source instead"),
+        format!("struct {gui_struct} {{"),
+    ];
+    header.extend(slots.iter().map(|slot| format!("    {slot}")));
+    header.extend(["};".to_owned(), String::new()]);
+    if !options.is_empty() {
+        header.push("// Shadey".to_owned());
+        header.extend(options.iter().map(|option| format!("// {option}")));
+    }
+    header.join("\n") + "\n\n"
+}
+
+/// The WGSL shadey compiles for a Shadertoy GLSL source, error lines are
+/// those of the GLSL
+pub fn to_wgsl(glsl: &str) -> Result<String, Diagnostic> {
+    let options = parser::parse_options(glsl)
+        .map(|(_, options)| options)
+        .unwrap_or_default();
+    let channels = channel_groups(&options).map_err(|message| Diagnostic {
+        message,
+        line: None,
+    })?;
+
+    let wgsl = translate(&prelude(&channels), glsl, MAIN)?;
+    let options = glsl
+        .lines()
+        .skip_while(|line| line.trim() != "// Shadey")
+        .skip(1)
+        .take_while(|line| line.trim_start().starts_with("//"))
+        .map(|line| line.trim_start().trim_start_matches("//").trim().to_owned())
+        .collect::<Vec<_>>();
+    let header = wgsl_header(
+        "Shadertoy GLSL",
+        &[format!("{TIME_OFFSET}: f32, // range(min=0,max=60)")],
+        &options,
+    );
+
+    Ok(header + &wgsl)
+}
+
+#[allow(unused)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        shader::compose_source,
+        shadertoy::{is_shadertoy, to_wgsl},
+    };
+
+    const PLASMA: &str = "\
+float wave(float x) { return 0.5 + 0.5 * sin(x); }
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    float time = iTime + float(iFrame) * 0.0;
+    fragColor = vec4(wave(uv.x * 10.0 + time), uv.y, iMouse.x, 1.0);
+}
+";
+
+    #[test]
+    fn shadertoy_extensions() {
+        assert!(is_shadertoy(Path::new("shader/plasma.glsl")));
+        assert!(!is_shadertoy(Path::new("shader/voronoi.wgsl")));
+    }
+
+    #[test]
+    fn translated_shader_composes() {
+        let wgsl = to_wgsl(PLASMA).unwrap();
+        let (shader, _) = compose_source(&wgsl).unwrap();
+
+        assert!(shader.check().is_ok());
+        assert!(shader.as_ref().contains("fn fs_main("));
+    }
+
+    #[test]
+    fn errors_located_in_glsl() {
+        let diagnostic = to_wgsl("void mainImage(out vec4 c, in vec2 p) {\n    c = oops;\n}\n");
+        assert!(diagnostic.unwrap_err().line == Some(2));
+    }
+}
@@ -0,0 +1,397 @@
+//! Shadertoy's JSON export of a whole shader: the Image pass, Buffers A to D,
+//! Common and the channel inputs with their sampler settings. Each pass is
+//! written as a Shadertoy GLSL file next to the export, with Common pasted at
+//! the top as shadey has no includes, and the inputs become texture options.
+//!
+//! shadey renders a single pass, buffers are written to be opened on their
+//! own but nothing feeds a channel reading one. It gets a transparent
+//! placeholder, or the image found at `<export>.buffer_a.png`, e.g. a still
+//! saved from the buffer. Sound and cubemap passes, keyboard, audio, webcam
+//! and video inputs aren't supported.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::shadertoy;
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ExportFile {
+    // as returned by the Shadertoy API
+    Api {
+        #[serde(rename = "Shader")]
+        shader: Export,
+    },
+    Many(Vec<Export>),
+    One(Export),
+}
+
+#[derive(Debug, Deserialize)]
+struct Export {
+    info: Info,
+    renderpass: Vec<RenderPass>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Info {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RenderPass {
+    #[serde(default)]
+    inputs: Vec<Input>,
+    #[serde(default)]
+    outputs: Vec<PassOutput>,
+    code: String,
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Input {
+    id: serde_json::Value,
+    #[serde(alias = "filepath")]
+    src: String,
+    #[serde(alias = "type")]
+    ctype: String,
+    channel: usize,
+    #[serde(default)]
+    sampler: Sampler,
+}
+
+#[derive(Debug, Deserialize)]
+struct PassOutput {
+    id: serde_json::Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Sampler {
+    filter: String,
+    wrap: String,
+    vflip: String,
+    srgb: String,
+}
+
+/// What an import wrote, and what couldn't be carried over
+#[derive(Debug, Default)]
+pub struct Import {
+    /// The Image pass, the one to open
+    pub image: PathBuf,
+    pub written: Vec<PathBuf>,
+    pub notes: Vec<String>,
+}
+
+impl Import {
+    pub fn report(&self) {
+        for path in &self.written {
+            eprintln!("Wrote {path:?}");
+        }
+        for note in &self.notes {
+            eprintln!("  {note}");
+        }
+    }
+}
+
+/// `Buffer A` -> `buffer_a`
+fn slug(pass_name: &str) -> String {
+    pass_name.trim().to_lowercase().replace(' ', "_")
+}
+
+/// `shader/export.json` -> `shader/export.glsl`, buffers get
+/// `shader/export.buffer_a.glsl`
+fn pass_path(json_path: &Path, pass: &RenderPass) -> PathBuf {
+    match pass.kind.as_str() {
+        "image" => json_path.with_extension("glsl"),
+        _ => json_path.with_extension(format!("{}.glsl", slug(&pass.name))),
+    }
+}
+
+/// Media of the Shadertoy site are looked for in the texture directory,
+/// under the same file name
+fn media_path(src: &str) -> String {
+    let file = Path::new(src)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    format!("texture/{file}")
+}
+
+/// The option line declaring an input, notes are added for what's lost
+fn channel_option(
+    input: &Input,
+    passes: &[RenderPass],
+    json_path: &Path,
+    notes: &mut Vec<String>,
+) -> Option<String> {
+    let name = format!("iChannel{}", input.channel);
+    let sampler = &input.sampler;
+    match input.ctype.as_str() {
+        "texture" => {
+            let mut option = format!("texture(path={}, name={name}", media_path(&input.src));
+            if sampler.wrap == "repeat" {
+                option += ", u_mode=repeat, v_mode=repeat";
+            }
+            if sampler.vflip == "true" {
+                option += ", vflip=true";
+            }
+            if sampler.filter == "nearest" || sampler.filter == "mipmap" {
+                notes.push(format!(
+                    "{name}: {} filtering is approximated, textures have no mipmaps",
+                    sampler.filter
+                ));
+            }
+            if sampler.srgb == "false" {
+                notes.push(format!("{name}: textures are always decoded from sRGB"));
+            }
+            Some(option + ")")
+        }
+        "cubemap" if input.src.starts_with("/media/a/") => {
+            // faces are stored as <hash>.png, <hash>_1.png, ... in wgpu layer order
+            let first = media_path(&input.src);
+            let (stem, extension) = first.rsplit_once('.').unwrap_or((&first, "png"));
+            let faces = ["px", "nx", "py", "ny", "pz", "nz"]
+                .iter()
+                .enumerate()
+                .map(|(i, face)| match i {
+                    0 => format!("{face}={first}"),
+                    _ => format!("{face}={stem}_{i}.{extension}"),
+                })
+                .collect::<Vec<_>>();
+            Some(format!("cubemap(name={name}, {})", faces.join(", ")))
+        }
+        "volume" => {
+            let path = media_path(&input.src);
+            let slices = path
+                .rsplit_once('.')
+                .map_or(path.as_str(), |(stem, _)| stem);
+            notes.push(format!(
+                "{name}: the volume {path} must be unpacked into slices in {slices}/"
+            ));
+            Some(format!("volume(path={slices}, name={name})"))
+        }
+        "buffer" => {
+            let Some(buffer) = passes
+                .iter()
+                .find(|pass| pass.outputs.iter().any(|output| output.id == input.id))
+            else {
+                notes.push(format!("{name}: the buffer it reads wasn't found"));
+                return None;
+            };
+            let still = json_path.with_extension(format!("{}.png", slug(&buffer.name)));
+            notes.push(format!(
+                "{name}: {} isn't rendered into it, shadey has no multipass. \
+                 A transparent placeholder is bound until {still:?} holds a still of it",
+                buffer.name
+            ));
+            Some(format!("texture(path={}, name={name})", still.display()))
+        }
+        ctype => {
+            notes.push(format!("{name}: {ctype} inputs aren't supported"));
+            None
+        }
+    }
+}
+
+// path and content of a written pass
+type PassFile = (PathBuf, String);
+
+/// Path and content of each pass, the Image first
+fn convert(export: &Export, json_path: &Path) -> Result<(Vec<PassFile>, Vec<String>), String> {
+    let mut notes = Vec::new();
+    let common = export
+        .renderpass
+        .iter()
+        .filter(|pass| pass.kind == "common")
+        .map(|pass| pass.code.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut passes = export
+        .renderpass
+        .iter()
+        .filter(|pass| match pass.kind.as_str() {
+            "image" | "buffer" => true,
+            "common" => false,
+            _ => {
+                notes.push(format!(
+                    "{}: {} passes aren't supported",
+                    pass.name, pass.kind
+                ));
+                false
+            }
+        })
+        .collect::<Vec<_>>();
+    passes.sort_by_key(|pass| pass.kind != "image");
+    if !passes.iter().any(|pass| pass.kind == "image") {
+        return Err("The export has no Image pass".to_owned());
+    }
+
+    let files = passes
+        .into_iter()
+        .map(|pass| {
+            let mut inputs = pass.inputs.iter().collect::<Vec<_>>();
+            inputs.sort_by_key(|input| input.channel);
+            let options = inputs
+                .into_iter()
+                .filter_map(|input| {
+                    channel_option(input, &export.renderpass, json_path, &mut notes)
+                })
+                .map(|option| format!("// {option}"))
+                .collect::<Vec<_>>();
+
+            let mut lines = vec![format!(
+                "// {} ({}), {} pass imported from a Shadertoy export",
+                export.info.name, export.info.id, pass.name
+            )];
+            if !options.is_empty() {
+                lines.push("// Shadey".to_owned());
+                lines.extend(options);
+            }
+            lines.push(String::new());
+            if !common.is_empty() {
+                lines.extend(["// Common".to_owned(), common.clone(), String::new()]);
+            }
+            lines.push(pass.code.clone());
+            (pass_path(json_path, pass), lines.join("\n"))
+        })
+        .collect();
+    Ok((files, notes))
+}
+
+/// Write the passes of a Shadertoy export next to it, each is checked to
+/// compile and failures are noted. Nothing is written when a pass file
+/// already exists.
+pub fn import(json_path: &Path) -> Result<Import, String> {
+    let content = std::fs::read_to_string(json_path)
+        .map_err(|e| format!("{json_path:?} couldn't be read: {e}"))?;
+    let mut skipped = None;
+    let export = match serde_json::from_str(&content)
+        .map_err(|e| format!("{json_path:?} isn't a Shadertoy export: {e}"))?
+    {
+        ExportFile::Api { shader } | ExportFile::One(shader) => shader,
+        ExportFile::Many(shaders) => {
+            let count = shaders.len();
+            let shader = shaders
+                .into_iter()
+                .next()
+                .ok_or("The export holds no shader")?;
+            if count > 1 {
+                skipped = Some(format!(
+                    "The export holds {count} shaders, only {} is imported",
+                    shader.info.name
+                ));
+            }
+            shader
+        }
+    };
+
+    let (files, mut notes) = convert(&export, json_path)?;
+    notes.splice(0..0, skipped);
+    if let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+        return Err(format!(
+            "{path:?} already exists, move it away to import {json_path:?} again"
+        ));
+    }
+    let mut import = Import::default();
+    for (path, content) in files {
+        std::fs::write(&path, &content)
+            .map_err(|e| format!("{path:?} couldn't be written: {e}"))?;
+        if let Err(diagnostic) = shadertoy::to_wgsl(&content) {
+            let line = diagnostic
+                .line
+                .map(|l| format!(" line {l}"))
+                .unwrap_or_default();
+            notes.push(format!(
+                "{path:?}{line} doesn't compile: {}",
+                diagnostic.message
+            ));
+        }
+        if import.written.is_empty() {
+            import.image = path.clone();
+        }
+        import.written.push(path);
+    }
+    import.notes = notes;
+    Ok(import)
+}
+
+#[allow(unused)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        shadertoy::to_wgsl,
+        shadertoy_json::{convert, ExportFile, PassFile},
+    };
+
+    const EXPORT: &str = r#"{"Shader": {
+        "info": {"id": "abcd12", "name": "Trails"},
+        "renderpass": [
+            {"name": "Image", "type": "image", "outputs": [{"id": 37, "channel": 0}],
+             "inputs": [
+                {"id": 257, "src": "/media/previz/buffer00.png", "ctype": "buffer", "channel": 0,
+                 "sampler": {"filter": "linear", "wrap": "clamp", "vflip": "true", "srgb": "false"}},
+                {"id": 8, "src": "/media/a/noise.png", "ctype": "texture", "channel": 1,
+                 "sampler": {"filter": "mipmap", "wrap": "repeat", "vflip": "true", "srgb": "false"}},
+                {"id": 33, "src": "/presets/tex00.jpg", "ctype": "keyboard", "channel": 2}
+             ],
+             "code": "void mainImage(out vec4 c, in vec2 p) {\n    c = texture(iChannel0, p / iResolution.xy) * tint(texture(iChannel1, p).x);\n}\n"},
+            {"name": "Common", "type": "common", "inputs": [], "outputs": [],
+             "code": "vec4 tint(float x) { return vec4(x, 0.5, 1.0, 1.0); }\n"},
+            {"name": "Buffer A", "type": "buffer", "outputs": [{"id": 257, "channel": 0}], "inputs": [],
+             "code": "void mainImage(out vec4 c, in vec2 p) {\n    c = tint(sin(iTime));\n}\n"},
+            {"name": "Sound", "type": "sound", "inputs": [], "outputs": [], "code": ""}
+        ]
+    }}"#;
+
+    fn converted() -> (Vec<PassFile>, Vec<String>) {
+        let ExportFile::Api { shader } = serde_json::from_str(EXPORT).unwrap() else {
+            panic!("The export should be read as an API response");
+        };
+        convert(&shader, Path::new("shader/trails.json")).unwrap()
+    }
+
+    #[test]
+    fn passes_and_channels() {
+        let (files, notes) = converted();
+        let paths = files
+            .iter()
+            .map(|(path, _)| path.as_path())
+            .collect::<Vec<_>>();
+        assert!(
+            paths
+                == [
+                    Path::new("shader/trails.glsl"),
+                    Path::new("shader/trails.buffer_a.glsl")
+                ]
+        );
+
+        let image = &files[0].1;
+        assert!(image.contains("// texture(path=shader/trails.buffer_a.png, name=iChannel0)\n"));
+        assert!(image.contains(
+            "// texture(path=texture/noise.png, name=iChannel1, u_mode=repeat, v_mode=repeat, vflip=true)\n"
+        ));
+        assert!(files
+            .iter()
+            .all(|(_, content)| content.contains("vec4 tint(float x)")));
+        assert!(notes
+            .iter()
+            .any(|note| note.starts_with("iChannel2: keyboard")));
+        assert!(notes.iter().any(|note| note.starts_with("Sound: sound")));
+        assert!(notes
+            .iter()
+            .any(|note| note.starts_with("iChannel0: Buffer A isn't rendered")));
+    }
+
+    #[test]
+    fn passes_translate() {
+        let (files, _) = converted();
+        assert!(files.iter().all(|(_, content)| to_wgsl(content).is_ok()));
+    }
+}
@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+
+pub const MAX_STEPS: u32 = 16;
+const PADDING: u32 = 4;
+const LABEL_HEIGHT: u32 = 16;
+const TITLE_HEIGHT: u32 = 24;
+
+/// One swept scalar slot of `GuiControlled`
+#[derive(Debug, Clone)]
+pub struct SweepAxis {
+    pub slot: String,
+    pub range: [f32; 2],
+    pub steps: u32,
+}
+
+impl SweepAxis {
+    pub fn value(&self, step: u32) -> f32 {
+        let [start, end] = self.range;
+        if self.steps <= 1 {
+            return start;
+        }
+        start + (end - start) * step as f32 / (self.steps - 1) as f32
+    }
+}
+
+/// A grid of thumbnails rendered across the values of one or two slots
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub x: Option<SweepAxis>,
+    pub y: Option<SweepAxis>,
+    pub thumbnail_size: [u32; 2],
+}
+
+impl Default for Sweep {
+    fn default() -> Self {
+        Self {
+            x: None,
+            y: None,
+            thumbnail_size: [256, 144],
+        }
+    }
+}
+
+impl Sweep {
+    pub fn columns(&self) -> u32 {
+        self.x.as_ref().map_or(1, |x| x.steps.clamp(1, MAX_STEPS))
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.y.as_ref().map_or(1, |y| y.steps.clamp(1, MAX_STEPS))
+    }
+
+    /// Slot values of each cell, row by row
+    pub fn cells(&self) -> Vec<Vec<(String, f32)>> {
+        let mut cells = Vec::new();
+        for row in 0..self.rows() {
+            for column in 0..self.columns() {
+                let x = self.x.iter().map(|x| (x.slot.clone(), x.value(column)));
+                let y = self.y.iter().map(|y| (y.slot.clone(), y.value(row)));
+                cells.push(x.chain(y).collect());
+            }
+        }
+        cells
+    }
+
+    /// Next to the shader, e.g. `voronoi.sweep-speed-influence.png`
+    pub fn output_path(&self, shader_path: &Path) -> PathBuf {
+        let slots = self
+            .x
+            .iter()
+            .chain(self.y.iter())
+            .map(|axis| axis.slot.as_str())
+            .collect::<Vec<_>>();
+        shader_path.with_extension(format!("sweep-{}.png", slots.join("-")))
+    }
+
+    pub fn title(&self, shader_path: &Path) -> String {
+        let axes = [("x", &self.x), ("y", &self.y)]
+            .into_iter()
+            .filter_map(|(name, axis)| {
+                let axis = axis.as_ref()?;
+                Some(format!(
+                    "{name}: {} {}..{} in {} steps",
+                    axis.slot, axis.range[0], axis.range[1], axis.steps
+                ))
+            })
+            .collect::<Vec<_>>();
+        let shader = shader_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        format!("{shader}  {}", axes.join("  "))
+    }
+
+    /// Lay out the rendered thumbnails, in `cells` order, with their values under them
+    pub fn compose(&self, title: &str, thumbnails: &[RgbaImage]) -> RgbaImage {
+        let [width, height] = self.thumbnail_size;
+        let label_height = LABEL_HEIGHT * (self.x.is_some() as u32 + self.y.is_some() as u32);
+        let (cell_width, cell_height) = (width + PADDING, height + label_height + PADDING);
+        let mut sheet = RgbaImage::from_pixel(
+            self.columns() * cell_width + PADDING,
+            self.rows() * cell_height + TITLE_HEIGHT + PADDING,
+            Rgba([24, 24, 24, 255]),
+        );
+
+        let text = TextPainter::new();
+        text.draw(&mut sheet, PADDING, PADDING, 16.0, title);
+        for (i, (cell, thumbnail)) in self.cells().iter().zip(thumbnails).enumerate() {
+            let (column, row) = (i as u32 % self.columns(), i as u32 / self.columns());
+            let x = PADDING + column * cell_width;
+            let y = TITLE_HEIGHT + PADDING + row * cell_height;
+            image::imageops::replace(&mut sheet, thumbnail, x as _, y as _);
+
+            // one line per axis so labels fit narrow thumbnails
+            for (line, (slot, value)) in cell.iter().enumerate() {
+                let label_y = y + height + 2 + line as u32 * LABEL_HEIGHT;
+                text.draw(&mut sheet, x, label_y, 13.0, &format!("{slot}={value:.3}"));
+            }
+        }
+        sheet
+    }
+}
+
+/// Rasterizes text with egui's built-in monospace font
+struct TextPainter {
+    font_data: egui::FontDefinitions,
+}
+
+impl TextPainter {
+    fn new() -> Self {
+        Self {
+            font_data: egui::FontDefinitions::default(),
+        }
+    }
+
+    fn draw(&self, image: &mut RgbaImage, x: u32, y: u32, size: f32, text: &str) {
+        let Some(font) = self
+            .font_data
+            .font_data
+            .get("Hack")
+            .and_then(|data| FontRef::try_from_slice(&data.font).ok())
+        else {
+            return;
+        };
+        let font = font.as_scaled(PxScale::from(size));
+
+        let mut caret = x as f32;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            let glyph = id.with_scale_and_position(size, (caret, y as f32 + font.ascent()));
+            caret += font.h_advance(id);
+
+            let Some(outline) = font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let (px, py) = (
+                    bounds.min.x as i32 + gx as i32,
+                    bounds.min.y as i32 + gy as i32,
+                );
+                if px < 0 || py < 0 || px as u32 >= image.width() || py as u32 >= image.height() {
+                    return;
+                }
+                let pixel = image.get_pixel_mut(px as _, py as _);
+                for channel in &mut pixel.0[..3] {
+                    *channel = (*channel as f32 + (255.0 - *channel as f32) * coverage) as u8;
+                }
+            });
+        }
+    }
+}
+
+#[allow(unused)]
+mod tests {
+    use std::path::Path;
+
+    use crate::sweep::{Sweep, SweepAxis};
+
+    fn sweep() -> Sweep {
+        Sweep {
+            x: Some(SweepAxis {
+                slot: "speed".to_owned(),
+                range: [0.0, 1.0],
+                steps: 3,
+            }),
+            y: Some(SweepAxis {
+                slot: "influence".to_owned(),
+                range: [2.0, 4.0],
+                steps: 2,
+            }),
+            thumbnail_size: [8, 8],
+        }
+    }
+
+    #[test]
+    fn cells_row_by_row() {
+        let cells = sweep().cells();
+
+        assert!(cells.len() == 6);
+        assert!(cells[1] == [("speed".to_owned(), 0.5), ("influence".to_owned(), 2.0)]);
+        assert!(cells[5] == [("speed".to_owned(), 1.0), ("influence".to_owned(), 4.0)]);
+    }
+
+    #[test]
+    fn sheet_next_to_shader() {
+        let path = sweep().output_path(Path::new("shader/voronoi.wgsl"));
+        assert!(path == Path::new("shader/voronoi.sweep-speed-influence.png"));
+    }
+}
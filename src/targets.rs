@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use naga::{back, valid::ModuleInfo, Module, ShaderStage};
+
+use super::shader::ShaderFileBuf;
+
+/// Shading languages the composed WGSL is translated to through naga's back-ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Target {
+    SpirV,
+    Glsl,
+    Hlsl,
+    Msl,
+}
+
+impl Target {
+    pub const ALL: [Target; 4] = [Target::SpirV, Target::Glsl, Target::Hlsl, Target::Msl];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Target::SpirV => "SPIR-V",
+            Target::Glsl => "GLSL",
+            Target::Hlsl => "HLSL",
+            Target::Msl => "MSL",
+        }
+    }
+}
+
+/// One translated file, GLSL gets one per entry point
+#[derive(Debug)]
+pub struct Output {
+    pub label: String,
+    pub extension: String,
+    pub text: String,
+    // written instead of the text on export
+    binary: Option<Vec<u8>>,
+}
+
+impl Output {
+    fn text(label: &str, extension: &str, text: String) -> Self {
+        Self {
+            label: label.to_owned(),
+            extension: extension.to_owned(),
+            text,
+            binary: None,
+        }
+    }
+
+    /// Next to the shader, e.g. `voronoi.fs_main.frag`
+    pub fn export_path(&self, shader_path: &Path) -> PathBuf {
+        shader_path.with_extension(&self.extension)
+    }
+
+    pub fn export(&self, shader_path: &Path) -> Option<PathBuf> {
+        let path = self.export_path(shader_path);
+        let written = match &self.binary {
+            Some(binary) => std::fs::write(&path, binary),
+            None => std::fs::write(&path, &self.text),
+        };
+        if let Err(e) = written {
+            eprintln!("{} couldn't be exported to {path:?}: {e}", self.label);
+            return None;
+        }
+        Some(path)
+    }
+}
+
+pub fn translate(
+    module: &Module,
+    info: &ModuleInfo,
+    target: Target,
+) -> Result<Vec<Output>, String> {
+    match target {
+        Target::SpirV => {
+            let words = back::spv::write_vec(module, info, &Default::default(), None)
+                .map_err(|e| e.to_string())?;
+            // naga has no disassembler, the words are shown as a dump
+            let dump = words
+                .chunks(8)
+                .map(|line| {
+                    let line = line.iter().map(|w| format!("{w:08x}")).collect::<Vec<_>>();
+                    line.join(" ")
+                })
+                .collect::<Vec<_>>();
+            Ok(vec![Output {
+                label: format!("SPIR-V ({} words)", words.len()),
+                extension: "spv".to_owned(),
+                text: dump.join("\n"),
+                binary: Some(bytemuck::cast_slice(&words).to_vec()),
+            }])
+        }
+        Target::Glsl => module
+            .entry_points
+            .iter()
+            .map(|entry_point| {
+                let options = back::glsl::Options {
+                    version: back::glsl::Version::Desktop(450),
+                    ..Default::default()
+                };
+                let pipeline_options = back::glsl::PipelineOptions {
+                    shader_stage: entry_point.stage,
+                    entry_point: entry_point.name.clone(),
+                    multiview: None,
+                };
+                let mut text = String::new();
+                back::glsl::Writer::new(
+                    &mut text,
+                    module,
+                    info,
+                    &options,
+                    &pipeline_options,
+                    Default::default(),
+                )
+                .and_then(|mut writer| writer.write())
+                .map_err(|e| format!("{}: {e}", entry_point.name))?;
+
+                let stage = match entry_point.stage {
+                    ShaderStage::Vertex => "vert",
+                    ShaderStage::Fragment => "frag",
+                    ShaderStage::Compute => "comp",
+                };
+                Ok(Output::text(
+                    &format!("{} ({stage})", entry_point.name),
+                    &format!("{}.{stage}", entry_point.name),
+                    text,
+                ))
+            })
+            .collect(),
+        Target::Hlsl => {
+            let mut text = String::new();
+            back::hlsl::Writer::new(&mut text, &Default::default())
+                .write(module, info)
+                .map_err(|e| e.to_string())?;
+            Ok(vec![Output::text("HLSL", "hlsl", text)])
+        }
+        Target::Msl => {
+            let (text, _) =
+                back::msl::write_string(module, info, &Default::default(), &Default::default())
+                    .map_err(|e| e.to_string())?;
+            Ok(vec![Output::text("MSL", "metal", text)])
+        }
+    }
+}
+
+/// Translations of the current shader, made when a tab is first shown
+#[derive(Default)]
+pub struct Targets {
+    pub visible: bool,
+    pub selected: Option<Target>,
+    outputs: BTreeMap<Target, Result<Vec<Output>, String>>,
+}
+
+impl Targets {
+    /// To be called whenever a new shader is compiled
+    pub fn invalidate(&mut self) {
+        self.outputs.clear();
+    }
+
+    pub fn outputs(
+        &mut self,
+        source: &ShaderFileBuf,
+        target: Target,
+    ) -> &Result<Vec<Output>, String> {
+        self.outputs.entry(target).or_insert_with(|| {
+            let (module, info) = source.module().map_err(|d| d.message)?;
+            translate(&module, &info, target)
+                .map_err(|e| format!("{} back-end failed: {e}", target.name()))
+        })
+    }
+}
+
+#[allow(unused)]
+mod tests {
+    use crate::{
+        shader::compose_source,
+        targets::{translate, Target},
+    };
+
+    #[test]
+    fn every_target_translates() {
+        let source = "struct GuiControlled {\n    speed: f32,\n};\n\n@fragment\nfn fs_main(vo: VertexOutput) -> @location(0) vec4<f32> {\n    return vec4<f32>(_gui.speed);\n}\n";
+        let (shader, _) = compose_source(source).unwrap();
+        let (module, info) = shader.module().unwrap();
+
+        for target in Target::ALL {
+            let outputs = translate(&module, &info, target).unwrap();
+            assert!(outputs.iter().all(|output| !output.text.is_empty()));
+        }
+        let glsl = translate(&module, &info, Target::Glsl).unwrap();
+        assert!(glsl.iter().any(|output| output.extension == "fs_main.frag"));
+    }
+}
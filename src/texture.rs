@@ -1,16 +1,84 @@
-use image::GenericImageView;
+use std::path::{Path, PathBuf};
 
-use super::parser::ShaderOptions;
+use image::{AnimationDecoder, GenericImageView, RgbaImage};
+
+use super::{
+    audio::{AudioTrack, AUDIO_TEXTURE_WIDTH},
+    parser::{CubemapSource, ShaderOptions, VolumeFormat},
+};
 
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
     pub name: String,
+    pub view_dimension: wgpu::TextureViewDimension,
+    pub animation: Option<Animation>,
+    pub audio: Option<AudioTrack>,
+}
+
+/// Frames of animated textures are decoded off the main thread, the texture
+/// starts as a single transparent layer until they are ready.
+pub struct Animation {
+    pub pending_frames: Option<AnimatedSource>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimatedSource {
+    Gif(PathBuf),
+    Sequence(Vec<PathBuf>),
+}
+
+const DEFAULT_SEQUENCE_FPS: f32 = 24.0;
+
+/// Speed of an animated texture: the option's, the gif's own, or the default
+fn animation_fps(fps: Option<f32>, source: &AnimatedSource) -> f32 {
+    fps.or_else(|| gif_fps(source))
+        .unwrap_or(DEFAULT_SEQUENCE_FPS)
+}
+
+/// A `// Shadey` option with its animation looked up once, the frames and
+/// gif delay read from disk, so the binding a source declares and the
+/// texture loaded for it agree
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureOption {
+    pub options: ShaderOptions,
+    /// Where the frames come from and their speed, for animated textures
+    pub animation: Option<(AnimatedSource, f32)>,
+}
+
+impl TextureOption {
+    pub fn resolve(options: ShaderOptions) -> Self {
+        let animation = match &options {
+            ShaderOptions::Texture { path, fps, .. } => animated_source(path).map(|source| {
+                let fps = animation_fps(*fps, &source);
+                (source, fps)
+            }),
+            _ => None,
+        };
+        Self { options, animation }
+    }
 }
 
 impl Texture {
-    pub fn generate_definition(&self, group_id: usize) -> String {
+    /// The bindings and helpers a texture option declares, no device needed;
+    /// None for the options that aren't textures.
+    pub fn generate_definition(option: &TextureOption, group_id: usize) -> Option<String> {
+        Some(match &option.options {
+            ShaderOptions::Texture { name, .. } => match &option.animation {
+                Some((_, fps)) => Self::generate_animated_definition(name, *fps, group_id),
+                None => Self::generate_2d_definition(name, group_id),
+            },
+            ShaderOptions::Cubemap { name, .. } => {
+                Self::generate_cubemap_definition(name, group_id)
+            }
+            ShaderOptions::Volume { name, .. } => Self::generate_volume_definition(name, group_id),
+            ShaderOptions::Audio { name, .. } => Self::generate_audio_definition(name, group_id),
+            ShaderOptions::Something => return None,
+        })
+    }
+
+    fn generate_2d_definition(name: &str, group_id: usize) -> String {
         format!(
             "@group({group_id}) @binding(0) \n\
             var t_diffuse{group_id}: texture_2d<f32>; \n\
@@ -26,7 +94,87 @@ impl Texture {
             }}"
             ,
             group_id=group_id+1,
-            name=self.name
+            name=name
+        )
+    }
+
+    fn generate_animated_definition(name: &str, fps: f32, group_id: usize) -> String {
+        format!(
+            "@group({group_id}) @binding(0) \n\
+            var t_diffuse{group_id}: texture_2d_array<f32>; \n\
+            @group({group_id}) @binding(1) \n\
+            var s_diffuse{group_id}: sampler; \n\
+            fn texture_{name}_size() -> vec2<f32> {{ return vec2<f32>(textureDimensions(t_diffuse{group_id}));}} \n\
+            fn texture_{name}_frames() -> u32 {{ return u32(textureNumLayers(t_diffuse{group_id}));}} \n\
+            fn texture_{name}_frame(vx: vec2<f32>, frame: u32) -> vec4<f32> \n\
+            {{ \n\
+                let texdim = texture_{name}_size(); \n\
+                let ratio = f32(texdim.x)/f32(texdim.y); \n\
+                let flip = flipy(vec2<f32>(vx.x, vx.y*ratio));
+                return textureSample(t_diffuse{group_id}, s_diffuse{group_id}, flip, i32(frame % texture_{name}_frames()));\n\
+            }} \n\
+            fn texture_{name}(vx: vec2<f32>) -> vec4<f32> \n\
+            {{ \n\
+                return texture_{name}_frame(vx, u32(time() * {fps:?}));\n\
+            }}"
+            ,
+            group_id=group_id+1,
+            name=name
+        )
+    }
+
+    fn generate_audio_definition(name: &str, group_id: usize) -> String {
+        format!(
+            "@group({group_id}) @binding(0) \n\
+            var t_diffuse{group_id}: texture_2d<f32>; \n\
+            @group({group_id}) @binding(1) \n\
+            var s_diffuse{group_id}: sampler; \n\
+            fn audio_{name}_fft(f: f32) -> f32 \n\
+            {{ \n\
+                return textureSampleLevel(t_diffuse{group_id}, s_diffuse{group_id}, vec2<f32>(f, 0.25), 0.0).x;\n\
+            }} \n\
+            fn audio_{name}_wave(t: f32) -> f32 \n\
+            {{ \n\
+                return textureSampleLevel(t_diffuse{group_id}, s_diffuse{group_id}, vec2<f32>(t, 0.75), 0.0).x;\n\
+            }}"
+            ,
+            group_id=group_id+1,
+            name=name
+        )
+    }
+
+    // textureSampleLevel so the helper stays usable inside raymarching loops
+    fn generate_cubemap_definition(name: &str, group_id: usize) -> String {
+        format!(
+            "@group({group_id}) @binding(0) \n\
+            var t_diffuse{group_id}: texture_cube<f32>; \n\
+            @group({group_id}) @binding(1) \n\
+            var s_diffuse{group_id}: sampler; \n\
+            fn cubemap_{name}_size() -> vec2<f32> {{ return vec2<f32>(textureDimensions(t_diffuse{group_id}));}} \n\
+            fn cubemap_{name}(dir: vec3<f32>) -> vec4<f32> \n\
+            {{ \n\
+                return textureSampleLevel(t_diffuse{group_id}, s_diffuse{group_id}, dir, 0.0);\n\
+            }}"
+            ,
+            group_id=group_id+1,
+            name=name
+        )
+    }
+
+    fn generate_volume_definition(name: &str, group_id: usize) -> String {
+        format!(
+            "@group({group_id}) @binding(0) \n\
+            var t_diffuse{group_id}: texture_3d<f32>; \n\
+            @group({group_id}) @binding(1) \n\
+            var s_diffuse{group_id}: sampler; \n\
+            fn volume_{name}_size() -> vec3<f32> {{ return vec3<f32>(textureDimensions(t_diffuse{group_id}));}} \n\
+            fn volume_{name}(p: vec3<f32>) -> vec4<f32> \n\
+            {{ \n\
+                return textureSampleLevel(t_diffuse{group_id}, s_diffuse{group_id}, p, 0.0);\n\
+            }}"
+            ,
+            group_id=group_id+1,
+            name=name
         )
     }
 }
@@ -35,15 +183,109 @@ impl Texture {
     pub fn from_shader_option(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        options: &ShaderOptions,
+        option: &TextureOption,
     ) -> Option<Self> {
-        if let ShaderOptions::Texture { path, .. } = options {
-            let texture_content = std::fs::read(path).ok()?;
-            return Self::from_bytes(device, queue, &texture_content, options);
+        let options = &option.options;
+        if let Some((source, _)) = &option.animation {
+            return Self::animated_placeholder(device, queue, options, source);
+        }
+        match options {
+            ShaderOptions::Texture { path, .. } => {
+                let texture_content = std::fs::read(path).ok()?;
+                Self::from_bytes(device, queue, &texture_content, options)
+            }
+            ShaderOptions::Cubemap { name, source } => {
+                let Some(faces) = load_cubemap_faces(source) else {
+                    eprintln!("Cubemap {name} couldn't be loaded from {source:?}");
+                    return None;
+                };
+                Self::from_cube_faces(device, queue, &faces, name)
+            }
+            ShaderOptions::Volume { path, name, .. } => {
+                let volume = load_volume(options);
+                if volume.is_none() {
+                    eprintln!("Volume {name} couldn't be loaded from {path:?}");
+                }
+                volume
+                    .and_then(|(data, size)| Self::from_volume(device, queue, &data, size, options))
+            }
+            ShaderOptions::Audio { path, name } => {
+                let Some(track) = AudioTrack::from_wav(path) else {
+                    eprintln!(
+                        "Audio {name} couldn't be decoded from {path:?}, only wav is supported"
+                    );
+                    return None;
+                };
+                Self::from_audio_track(device, track, name)
+            }
+            _ => None,
         }
+    }
+    /// A transparent texel of the option's kind, bound when loading fails so
+    /// the bind groups keep matching the declared bindings
+    pub fn placeholder(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        option: &TextureOption,
+    ) -> Option<Self> {
+        use wgpu::TextureViewDimension::*;
+        let (name, view_dimension) = match &option.options {
+            ShaderOptions::Texture { name, .. } if option.animation.is_some() => (name, D2Array),
+            ShaderOptions::Texture { name, .. } | ShaderOptions::Audio { name, .. } => (name, D2),
+            ShaderOptions::Cubemap { name, .. } => (name, Cube),
+            ShaderOptions::Volume { name, .. } => (name, D3),
+            ShaderOptions::Something => return None,
+        };
+        let layers = if view_dimension == Cube { 6 } else { 1 };
+        let size = wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: layers,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(name),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: match view_dimension {
+                D3 => wgpu::TextureDimension::D3,
+                _ => wgpu::TextureDimension::D2,
+            },
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &vec![0; 4 * layers as usize],
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4),
+                rows_per_image: Some(1),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
 
-        None
+        Some(Self {
+            texture,
+            view,
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor::default()),
+            name: name.to_owned(),
+            view_dimension,
+            animation: None,
+            audio: None,
+        })
     }
+
     pub fn from_bytes(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
@@ -66,10 +308,15 @@ impl Texture {
             u_addr_mode,
             v_addr_mode,
             w_addr_mode,
+            vflip,
             ..
         } = options
         {
-            let rgba = img.to_rgba8();
+            let rgba = if *vflip {
+                image::imageops::flip_vertical(&img.to_rgba8())
+            } else {
+                img.to_rgba8()
+            };
             let dimensions = img.dimensions();
 
             let size = wgpu::Extent3d {
@@ -118,9 +365,702 @@ impl Texture {
                 view,
                 sampler,
                 name: name.to_owned(),
+                view_dimension: wgpu::TextureViewDimension::D2,
+                animation: None,
+                audio: None,
             });
         }
 
         None
     }
+
+    /// Faces must be square, equally sized and in wgpu layer order (+X, -X, +Y, -Y, +Z, -Z).
+    pub fn from_cube_faces(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[RgbaImage; 6],
+        name: &str,
+    ) -> Option<Self> {
+        let face_size = faces[0].width();
+        if faces
+            .iter()
+            .any(|f| f.width() != face_size || f.height() != face_size)
+        {
+            eprintln!("Cubemap {name} faces must be square and of the same size");
+            return None;
+        }
+
+        let size = wgpu::Extent3d {
+            width: face_size,
+            height: face_size,
+            depth_or_array_layers: 6,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(name),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[wgpu::TextureFormat::Rgba8UnormSrgb],
+        });
+        for (layer, face) in faces.iter().enumerate() {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as _,
+                    },
+                },
+                face,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * face_size),
+                    rows_per_image: Some(face_size),
+                },
+                wgpu::Extent3d {
+                    depth_or_array_layers: 1,
+                    ..size
+                },
+            );
+        }
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Some(Self {
+            texture,
+            view,
+            sampler,
+            name: name.to_owned(),
+            view_dimension: wgpu::TextureViewDimension::Cube,
+            animation: None,
+            audio: None,
+        })
+    }
+}
+
+impl Texture {
+    /// `data` is tightly packed texels already in `format.gpu_format()`
+    pub fn from_volume(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[u8],
+        [width, height, depth]: [u32; 3],
+        options: &ShaderOptions,
+    ) -> Option<Self> {
+        let ShaderOptions::Volume {
+            name,
+            format,
+            u_addr_mode,
+            v_addr_mode,
+            w_addr_mode,
+            ..
+        } = options
+        else {
+            return None;
+        };
+
+        let gpu_format = format.gpu_format();
+        let texel_size = gpu_format.block_size(None)?;
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: depth,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(name),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: gpu_format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[gpu_format],
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(texel_size * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: u_addr_mode.unwrap_or(wgpu::AddressMode::ClampToEdge),
+            address_mode_v: v_addr_mode.unwrap_or(wgpu::AddressMode::ClampToEdge),
+            address_mode_w: w_addr_mode.unwrap_or(wgpu::AddressMode::ClampToEdge),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Some(Self {
+            texture,
+            view,
+            sampler,
+            name: name.to_owned(),
+            view_dimension: wgpu::TextureViewDimension::D3,
+            animation: None,
+            audio: None,
+        })
+    }
+}
+
+/// Reads a volume either from a raw binary file (`size` required) or from a
+/// folder of equally sized slice images sorted by file name.
+fn load_volume(options: &ShaderOptions) -> Option<(Vec<u8>, [u32; 3])> {
+    let ShaderOptions::Volume {
+        path, size, format, ..
+    } = options
+    else {
+        return None;
+    };
+
+    if path.is_dir() {
+        load_volume_slices(path, *size, *format)
+    } else {
+        load_volume_raw(path, (*size)?, *format)
+    }
+}
+
+fn load_volume_slices(
+    path: &Path,
+    size: Option<[u32; 3]>,
+    format: VolumeFormat,
+) -> Option<(Vec<u8>, [u32; 3])> {
+    let mut slice_paths = std::fs::read_dir(path)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .collect::<Vec<_>>();
+    slice_paths.sort();
+
+    let slices = slice_paths
+        .iter()
+        .filter_map(|p| image::open(p).ok())
+        .collect::<Vec<_>>();
+    let (width, height) = slices.first()?.dimensions();
+    if slices.iter().any(|s| s.dimensions() != (width, height)) {
+        eprintln!("Volume slices in {path:?} must all have the same size");
+        return None;
+    }
+    let volume_size = [width, height, slices.len() as u32];
+    if size.is_some_and(|s| s != volume_size) {
+        eprintln!("Volume slices in {path:?} don't match the declared size {size:?}");
+        return None;
+    }
+
+    let data = slices
+        .iter()
+        .flat_map(|slice| slice_texels(slice, format))
+        .collect();
+    Some((data, volume_size))
+}
+
+fn load_volume_raw(
+    path: &Path,
+    size: [u32; 3],
+    format: VolumeFormat,
+) -> Option<(Vec<u8>, [u32; 3])> {
+    let data = std::fs::read(path).ok()?;
+    let expected = size.iter().product::<u32>() as usize * format.texel_size();
+    if data.len() != expected {
+        eprintln!(
+            "Raw volume {path:?} is {} bytes, expected {expected} for {size:?} {format:?}",
+            data.len()
+        );
+        return None;
+    }
+
+    let data = match format {
+        VolumeFormat::R32F | VolumeFormat::Rgba32F => data
+            .chunks_exact(4)
+            .flat_map(|c| {
+                let value = f32::from_le_bytes(c.try_into().unwrap());
+                half::f16::from_f32(value).to_le_bytes()
+            })
+            .collect(),
+        _ => data,
+    };
+
+    Some((data, size))
+}
+
+fn slice_texels(slice: &image::DynamicImage, format: VolumeFormat) -> Vec<u8> {
+    let to_half = |v: &f32| half::f16::from_f32(*v).to_le_bytes();
+    match format {
+        VolumeFormat::R8 => slice.to_luma8().into_raw(),
+        VolumeFormat::Rgba8 => slice.to_rgba8().into_raw(),
+        VolumeFormat::R16F | VolumeFormat::R32F => {
+            slice.to_luma32f().iter().flat_map(to_half).collect()
+        }
+        VolumeFormat::Rgba16F | VolumeFormat::Rgba32F => {
+            slice.to_rgba32f().iter().flat_map(to_half).collect()
+        }
+    }
+}
+
+impl Texture {
+    fn animated_placeholder(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        options: &ShaderOptions,
+        source: &AnimatedSource,
+    ) -> Option<Self> {
+        let ShaderOptions::Texture {
+            name,
+            u_addr_mode,
+            v_addr_mode,
+            w_addr_mode,
+            ..
+        } = options
+        else {
+            return None;
+        };
+
+        let (texture, view) = create_frames_texture(device, queue, &[RgbaImage::new(1, 1)], name);
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: u_addr_mode.unwrap_or(wgpu::AddressMode::ClampToEdge),
+            address_mode_v: v_addr_mode.unwrap_or(wgpu::AddressMode::ClampToEdge),
+            address_mode_w: w_addr_mode.unwrap_or(wgpu::AddressMode::ClampToEdge),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Some(Self {
+            texture,
+            view,
+            sampler,
+            name: name.to_owned(),
+            view_dimension: wgpu::TextureViewDimension::D2Array,
+            animation: Some(Animation {
+                pending_frames: Some(source.clone()),
+            }),
+            audio: None,
+        })
+    }
+
+    /// Swap the layers of an animated texture once its frames are decoded
+    pub fn replace_frames(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        frames: &[RgbaImage],
+    ) {
+        let (texture, view) = create_frames_texture(device, queue, frames, &self.name);
+        self.texture = texture;
+        self.view = view;
+    }
+}
+
+impl Texture {
+    fn from_audio_track(device: &wgpu::Device, track: AudioTrack, name: &str) -> Option<Self> {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(name),
+            size: wgpu::Extent3d {
+                width: AUDIO_TEXTURE_WIDTH,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[wgpu::TextureFormat::R8Unorm],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Some(Self {
+            texture,
+            view,
+            sampler,
+            name: name.to_owned(),
+            view_dimension: wgpu::TextureViewDimension::D2,
+            animation: None,
+            audio: Some(track),
+        })
+    }
+
+    /// Re-analyse the audio track at `time` and upload it, no-op for other textures
+    pub fn update_audio(&mut self, queue: &wgpu::Queue, time: f32) {
+        let Some(track) = self.audio.as_mut() else {
+            return;
+        };
+
+        let texels = track.analyse(time);
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &texels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(AUDIO_TEXTURE_WIDTH),
+                rows_per_image: Some(2),
+            },
+            wgpu::Extent3d {
+                width: AUDIO_TEXTURE_WIDTH,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+fn create_frames_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    frames: &[RgbaImage],
+    name: &str,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let (width, height) = frames[0].dimensions();
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: frames.len() as _,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(name),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[wgpu::TextureFormat::Rgba8UnormSrgb],
+    });
+    for (layer, frame) in frames.iter().enumerate() {
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer as _,
+                },
+            },
+            frame,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..size
+            },
+        );
+    }
+    // a single layer would default to a D2 view
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        ..Default::default()
+    });
+
+    (texture, view)
+}
+
+/// Gifs and paths with `*`/`?` wildcards in their file name are animated
+pub fn animated_source(path: &Path) -> Option<AnimatedSource> {
+    let file_name = path.file_name()?.to_str()?;
+    if file_name.contains(['*', '?']) {
+        let dir = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        let mut frames = std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| wildcard_match(file_name, n))
+            })
+            .collect::<Vec<_>>();
+        frames.sort_by_key(|p| natural_key(p));
+
+        return (!frames.is_empty()).then_some(AnimatedSource::Sequence(frames));
+    }
+
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("gif"))
+        .then(|| AnimatedSource::Gif(path.to_owned()))
+}
+
+/// Blocking, meant to run on the thread pool
+pub fn decode_frames(source: &AnimatedSource) -> Option<Vec<RgbaImage>> {
+    let frames = match source {
+        AnimatedSource::Gif(path) => {
+            let file = std::io::BufReader::new(std::fs::File::open(path).ok()?);
+            let decoder = image::codecs::gif::GifDecoder::new(file).ok()?;
+            decoder
+                .into_frames()
+                .map(|f| f.ok().map(|f| f.into_buffer()))
+                .collect::<Option<Vec<_>>>()?
+        }
+        AnimatedSource::Sequence(paths) => paths
+            .iter()
+            .map(|p| image::open(p).ok().map(|i| i.to_rgba8()))
+            .collect::<Option<Vec<_>>>()?,
+    };
+
+    let dimensions = frames.first()?.dimensions();
+    if frames.iter().any(|f| f.dimensions() != dimensions) {
+        eprintln!("All frames of {source:?} must have the same size");
+        return None;
+    }
+
+    Some(frames)
+}
+
+/// Only decodes the first frame to get its delay
+fn gif_fps(source: &AnimatedSource) -> Option<f32> {
+    let AnimatedSource::Gif(path) = source else {
+        return None;
+    };
+    let file = std::io::BufReader::new(std::fs::File::open(path).ok()?);
+    let first_frame = image::codecs::gif::GifDecoder::new(file)
+        .ok()?
+        .into_frames()
+        .next()?
+        .ok()?;
+    let (numer, denom) = first_frame.delay().numer_denom_ms();
+
+    (numer > 0).then(|| 1000.0 * denom as f32 / numer as f32)
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    match (pattern.chars().next(), name.chars().next()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            wildcard_match(&pattern[1..], name)
+                || name
+                    .chars()
+                    .next()
+                    .is_some_and(|c| wildcard_match(pattern, &name[c.len_utf8()..]))
+        }
+        (Some('?'), Some(c)) => wildcard_match(&pattern[1..], &name[c.len_utf8()..]),
+        (Some(p), Some(c)) if p == c => {
+            wildcard_match(&pattern[p.len_utf8()..], &name[c.len_utf8()..])
+        }
+        _ => false,
+    }
+}
+
+/// Splits `frame_12.png` into `[("frame_", 12), (".png", 0)]` so that frame 2 sorts before frame 10
+fn natural_key(path: &Path) -> Vec<(String, u64)> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    let mut key = Vec::new();
+    let mut rest = name;
+    while !rest.is_empty() {
+        let text_end = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (text, after_text) = rest.split_at(text_end);
+        let digits_end = after_text
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after_text.len());
+        let (digits, after_digits) = after_text.split_at(digits_end);
+        key.push((text.to_owned(), digits.parse().unwrap_or(0)));
+        rest = after_digits;
+    }
+
+    key
+}
+
+const CUBE_FACE_NAMES: [[&str; 3]; 6] = [
+    ["px", "posx", "right"],
+    ["nx", "negx", "left"],
+    ["py", "posy", "top"],
+    ["ny", "negy", "bottom"],
+    ["pz", "posz", "front"],
+    ["nz", "negz", "back"],
+];
+
+fn load_cubemap_faces(source: &CubemapSource) -> Option<[RgbaImage; 6]> {
+    match source {
+        CubemapSource::Faces(paths) => load_faces(paths),
+        CubemapSource::Path(path) if path.is_dir() => {
+            let entries = std::fs::read_dir(path)
+                .ok()?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .collect::<Vec<_>>();
+            let find_face = |aliases: &[&str; 3]| {
+                entries
+                    .iter()
+                    .find(|p| {
+                        p.file_stem()
+                            .and_then(|s| s.to_str())
+                            .is_some_and(|s| aliases.contains(&s.to_lowercase().as_str()))
+                    })
+                    .cloned()
+            };
+            let paths = CUBE_FACE_NAMES
+                .iter()
+                .map(find_face)
+                .collect::<Option<Vec<_>>>()?;
+            load_faces(&paths)
+        }
+        CubemapSource::Path(path) => {
+            let img = image::open(path).ok()?.to_rgba8();
+            faces_from_single_image(&img)
+        }
+    }
+}
+
+fn load_faces<P: AsRef<Path>>(paths: &[P]) -> Option<[RgbaImage; 6]> {
+    let faces = paths
+        .iter()
+        .map(|p| image::open(p).ok().map(|i| i.to_rgba8()))
+        .collect::<Option<Vec<_>>>()?;
+    faces.try_into().ok()
+}
+
+/// Guess the layout of a single image from its aspect ratio:
+/// 2:1 equirect, 4:3 horizontal cross, 3:4 vertical cross, 6:1 or 1:6 strip.
+fn faces_from_single_image(img: &RgbaImage) -> Option<[RgbaImage; 6]> {
+    use image::imageops::{crop_imm, rotate180};
+
+    let (w, h) = img.dimensions();
+    let crop = |col: u32, row: u32, size: u32| {
+        crop_imm(img, col * size, row * size, size, size).to_image()
+    };
+    let faces = match (w, h) {
+        (w, h) if w == 2 * h => return Some(equirect_to_faces(img, h / 2)),
+        (w, h) if 3 * w == 4 * h => {
+            let s = w / 4;
+            vec![
+                crop(2, 1, s),
+                crop(0, 1, s),
+                crop(1, 0, s),
+                crop(1, 2, s),
+                crop(1, 1, s),
+                crop(3, 1, s),
+            ]
+        }
+        (w, h) if 4 * w == 3 * h => {
+            let s = w / 3;
+            // -Z sits under -Y upside down in the vertical cross
+            vec![
+                crop(2, 1, s),
+                crop(0, 1, s),
+                crop(1, 0, s),
+                crop(1, 2, s),
+                crop(1, 1, s),
+                rotate180(&crop(1, 3, s)),
+            ]
+        }
+        (w, h) if w == 6 * h => (0..6).map(|i| crop(i, 0, h)).collect(),
+        (w, h) if h == 6 * w => (0..6).map(|i| crop(0, i, w)).collect(),
+        _ => return None,
+    };
+
+    faces.try_into().ok()
+}
+
+fn equirect_to_faces(img: &RgbaImage, face_size: u32) -> [RgbaImage; 6] {
+    use std::f32::consts::PI;
+
+    let faces = (0..6).map(|face| {
+        RgbaImage::from_fn(face_size, face_size, |x, y| {
+            let s = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+            let t = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+            // inverse of the cube face selection table of the wgpu/vulkan spec
+            let dir = match face {
+                0 => [1.0, -t, -s],
+                1 => [-1.0, -t, s],
+                2 => [s, 1.0, t],
+                3 => [s, -1.0, -t],
+                4 => [s, -t, 1.0],
+                _ => [-s, -t, -1.0],
+            };
+            let len = (dir[0] * dir[0] + dir[1] * dir[1] + dir[2] * dir[2]).sqrt();
+            let u = 0.5 + dir[0].atan2(-dir[2]) / (2.0 * PI);
+            let v = (dir[1] / len).acos() / PI;
+            sample_bilinear(img, u, v)
+        })
+    });
+
+    faces.collect::<Vec<_>>().try_into().unwrap()
+}
+
+fn sample_bilinear(img: &RgbaImage, u: f32, v: f32) -> image::Rgba<u8> {
+    let (w, h) = img.dimensions();
+    let x = (u * w as f32 - 0.5).rem_euclid(w as f32);
+    let y = (v * h as f32 - 0.5).clamp(0.0, (h - 1) as f32);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1) % w, (y0 + 1).min(h - 1));
+    let (fx, fy) = (x.fract(), y.fract());
+
+    let p = |x, y| img.get_pixel(x, y).0.map(|c| c as f32);
+    let (a, b, c, d) = (p(x0, y0), p(x1, y0), p(x0, y1), p(x1, y1));
+    image::Rgba(std::array::from_fn(|i| {
+        let top = a[i] + (b[i] - a[i]) * fx;
+        let bottom = c[i] + (d[i] - c[i]) * fx;
+        (top + (bottom - top) * fy).round() as u8
+    }))
+}
+
+#[allow(unused)]
+mod tests {
+    use std::path::Path;
+
+    use crate::texture::{natural_key, wildcard_match};
+
+    #[test]
+    fn wildcard_matches_frames() {
+        assert!(wildcard_match("*.png", "frame_01.png"));
+        assert!(wildcard_match("frame_??.png", "frame_01.png"));
+        assert!(!wildcard_match("*.png", "frame_01.jpg"));
+        assert!(!wildcard_match("frame_?.png", "frame_01.png"));
+    }
+
+    #[test]
+    fn frames_sorted_naturally() {
+        let mut frames = vec!["f10.png", "f2.png", "f1.png"];
+        frames.sort_by_key(|f| natural_key(Path::new(f)));
+
+        assert!(frames == vec!["f1.png", "f2.png", "f10.png"]);
+    }
 }
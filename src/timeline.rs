@@ -0,0 +1,253 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    morph::interpolate,
+    wgsl::{RuntimeStruct, SlotValue},
+};
+
+// keys closer than this are considered at the same time
+const KEY_EPSILON: f32 = 1e-3;
+
+/// How a key eases into the next one
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpolation {
+    Linear,
+    Ease,
+    /// Control points x1, y1, x2, y2 of a css-like cubic-bezier()
+    Bezier([f32; 4]),
+}
+
+impl Interpolation {
+    pub fn ease(&self, progress: f32) -> f32 {
+        match self {
+            Interpolation::Linear => progress,
+            Interpolation::Ease => progress * progress * (3.0 - 2.0 * progress),
+            Interpolation::Bezier([x1, y1, x2, y2]) => {
+                let bezier = |a: f32, b: f32, t: f32| {
+                    3.0 * a * t * (1.0 - t).powi(2) + 3.0 * b * t * t * (1.0 - t) + t.powi(3)
+                };
+                // x(t) is monotonic for x1, x2 in [0, 1], find t where x(t) = progress
+                let (x1, x2) = (x1.clamp(0.0, 1.0), x2.clamp(0.0, 1.0));
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..24 {
+                    let mid = (low + high) / 2.0;
+                    if bezier(x1, x2, mid) < progress {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                bezier(*y1, *y2, (low + high) / 2.0)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub time: f32,
+    pub value: SlotValue,
+    pub interpolation: Interpolation,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TimelineFile {
+    #[serde(default)]
+    tracks: BTreeMap<String, Vec<Keyframe>>,
+}
+
+/// Keyframes of the `GuiControlled` slots by slot name, kept in a sidecar file
+/// next to the shader (`voronoi.wgsl` -> `voronoi.timeline.json`)
+pub struct Timeline {
+    path: PathBuf,
+    file: TimelineFile,
+    pub selected: Option<(String, usize)>,
+    pub visible: bool,
+    // the values are only re-applied when time moves or keys change, so
+    // sliders stay editable while paused
+    applied_at: Option<f32>,
+}
+
+impl Timeline {
+    pub fn sidecar_path(shader_path: &Path) -> PathBuf {
+        shader_path.with_extension("timeline.json")
+    }
+
+    /// Starts empty when the sidecar is missing or unreadable
+    pub fn for_shader(shader_path: &Path) -> Self {
+        let path = Self::sidecar_path(shader_path);
+        let file = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                eprintln!("Timeline file {path:?} couldn't be parsed: {e}");
+                TimelineFile::default()
+            }),
+            Err(_) => TimelineFile::default(),
+        };
+
+        Self {
+            path,
+            file,
+            selected: None,
+            visible: false,
+            applied_at: None,
+        }
+    }
+
+    pub fn keys(&self, name: &str) -> &[Keyframe] {
+        self.file.tracks.get(name).map_or(&[], |keys| &keys[..])
+    }
+
+    /// The slots with at least one key
+    pub fn tracks(&self) -> impl Iterator<Item = &str> {
+        self.file
+            .tracks
+            .iter()
+            .filter(|(_, keys)| !keys.is_empty())
+            .map(|(name, _)| name.as_str())
+    }
+
+    pub fn end_time(&self) -> f32 {
+        self.file
+            .tracks
+            .values()
+            .filter_map(|keys| keys.last())
+            .map(|key| key.time)
+            .fold(0.0, f32::max)
+    }
+
+    /// Replaces a key at the same time, returns the key index
+    pub fn set_key(&mut self, name: &str, key: Keyframe) -> usize {
+        let keys = self.file.tracks.entry(name.to_owned()).or_default();
+        keys.retain(|k| (k.time - key.time).abs() > KEY_EPSILON);
+        let index = keys.partition_point(|k| k.time < key.time);
+        keys.insert(index, key);
+
+        self.changed();
+        index
+    }
+
+    pub fn remove_key(&mut self, name: &str, index: usize) {
+        let Some(keys) = self.file.tracks.get_mut(name) else {
+            return;
+        };
+        if index < keys.len() {
+            keys.remove(index);
+        }
+        if keys.is_empty() {
+            self.file.tracks.remove(name);
+        }
+        self.selected = None;
+        self.changed();
+    }
+
+    pub fn value_at(&self, name: &str, gui_struct: &RuntimeStruct, time: f32) -> Option<SlotValue> {
+        let keys = self.keys(name);
+        let slot = gui_struct.get_slot_number(name)?;
+        let typed = &gui_struct.slots[slot - 1].typed;
+
+        let next = keys.partition_point(|k| k.time <= time);
+        match (keys.get(next.wrapping_sub(1)), keys.get(next)) {
+            (Some(from), Some(to)) => {
+                let progress = (time - from.time) / (to.time - from.time);
+                let eased = from.interpolation.ease(progress);
+                interpolate(typed, &from.value, &to.value, eased)
+            }
+            (Some(key), None) | (None, Some(key)) => Some(key.value.clone()),
+            (None, None) => None,
+        }
+    }
+
+    /// Write the animated values, to be done before the struct is uploaded
+    pub fn apply(&mut self, time: f32, gui_struct: &mut RuntimeStruct) {
+        if self.applied_at == Some(time) {
+            return;
+        }
+        self.applied_at = Some(time);
+
+        for name in self.file.tracks.keys() {
+            let Some(value) = self.value_at(name, gui_struct, time) else {
+                continue;
+            };
+            if let Some(slot) = gui_struct.get_slot_number(name) {
+                gui_struct.set_slot_value(slot, &value);
+            }
+        }
+    }
+
+    fn changed(&mut self) {
+        self.applied_at = None;
+
+        let content = serde_json::to_string_pretty(&self.file).expect("Keyframes are serializable");
+        if let Err(e) = std::fs::write(&self.path, content) {
+            eprintln!("Timeline couldn't be saved to {:?}: {e}", self.path);
+        }
+    }
+}
+
+#[allow(unused)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        timeline::{Interpolation, Keyframe, Timeline, TimelineFile},
+        wgsl::{PType::*, RuntimeStruct, SlotValue, StructSlot, TType::*},
+    };
+
+    fn key(time: f32, value: f64, interpolation: Interpolation) -> Keyframe {
+        Keyframe {
+            time,
+            value: SlotValue::Scalar(value),
+            interpolation,
+        }
+    }
+
+    fn timeline(keys: Vec<Keyframe>) -> Timeline {
+        Timeline {
+            path: PathBuf::new(),
+            file: TimelineFile {
+                tracks: [("speed".to_owned(), keys)].into(),
+            },
+            selected: None,
+            visible: false,
+            applied_at: None,
+        }
+    }
+
+    fn gui_struct() -> RuntimeStruct {
+        RuntimeStruct::new(vec![StructSlot {
+            identifier: "speed".to_owned(),
+            typed: Scalar(F32),
+            options: None,
+            default: None,
+        }])
+    }
+
+    #[test]
+    fn linear_between_keys() {
+        let timeline = timeline(vec![
+            key(1.0, 0.0, Interpolation::Linear),
+            key(3.0, 4.0, Interpolation::Linear),
+        ]);
+        let at = |time| timeline.value_at("speed", &gui_struct(), time);
+
+        assert!(at(2.0) == Some(SlotValue::Scalar(2.0)));
+        assert!(at(0.0) == Some(SlotValue::Scalar(0.0)));
+        assert!(at(5.0) == Some(SlotValue::Scalar(4.0)));
+    }
+
+    #[test]
+    fn bezier_ease_keeps_ends() {
+        let bezier = Interpolation::Bezier([0.42, 0.0, 0.58, 1.0]);
+
+        assert!(bezier.ease(0.0).abs() < 1e-4);
+        assert!((bezier.ease(1.0) - 1.0).abs() < 1e-4);
+        assert!((bezier.ease(0.5) - 0.5).abs() < 1e-4);
+        assert!(bezier.ease(0.25) < 0.25);
+    }
+}
@@ -0,0 +1,244 @@
+use std::ops::RangeInclusive;
+
+/// Shader time moved by a single frame step of a real time clock
+pub const STEP_DURATION: f32 = 1.0 / 60.0;
+/// Frame rates a fixed step clock accepts, from the UI and the CLI
+pub const FIXED_FPS: RangeInclusive<u32> = 1..=240;
+
+/// Where the time of each frame comes from, before the transport scales it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClockMode {
+    /// Wall clock time elapsed since the previous frame
+    RealTime,
+    /// Exactly 1/fps per rendered frame, for reproducible renders and
+    /// profiling. The transport's speed doesn't apply.
+    FixedStep { fps: u32 },
+    /// Only moves when scrubbed or stepped
+    Scrubbed,
+}
+
+impl ClockMode {
+    /// Shader time and frames a played frame moves by, none when scrubbed
+    fn frame_delta(&self, real_delta: f32, speed: f32) -> (f32, i32) {
+        match self {
+            ClockMode::RealTime => (real_delta * speed, 1),
+            ClockMode::FixedStep { fps } => (1.0 / *fps as f32, 1),
+            ClockMode::Scrubbed => (0.0, 0),
+        }
+    }
+
+    pub fn step_duration(&self) -> f32 {
+        match self {
+            ClockMode::FixedStep { fps } => 1.0 / *fps as f32,
+            _ => STEP_DURATION,
+        }
+    }
+}
+
+/// Play/pause, scrubbing, speed and looping of the shader's `time`
+pub struct Transport {
+    pub clock_mode: ClockMode,
+    pub playing: bool,
+    pub speed: f32,
+    pub time: f32,
+    pub frame: u32,
+    pub looping: bool,
+    pub loop_range: [f32; 2],
+    pub reset_on_reload: bool,
+    pending_steps: i32,
+    // a fixed step clock derives time from the frame count so it doesn't
+    // drift: `origin + frame / fps`, re-anchored when time is moved elsewhere
+    fixed_origin: f64,
+    fixed_time: Option<f32>,
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self {
+            clock_mode: ClockMode::RealTime,
+            playing: true,
+            speed: 1.0,
+            time: 0.0,
+            frame: 0,
+            looping: false,
+            loop_range: [0.0, 10.0],
+            reset_on_reload: false,
+            pending_steps: 0,
+            fixed_origin: 0.0,
+            fixed_time: None,
+        }
+    }
+}
+
+impl Transport {
+    pub fn toggle_play(&mut self) {
+        self.playing = !self.playing;
+    }
+
+    pub fn reset(&mut self) {
+        self.time = if self.looping {
+            self.loop_range[0]
+        } else {
+            0.0
+        };
+        self.frame = 0;
+    }
+
+    /// Queue single frame steps, negative to go back, applied on the next advance
+    pub fn step(&mut self, frames: i32) {
+        self.pending_steps += frames;
+    }
+
+    /// Move time forward by `real_delta` seconds of wall clock, returns how
+    /// much shader time actually passed
+    pub fn advance(&mut self, real_delta: f32) -> f32 {
+        let (mut delta, mut frames) = if self.playing {
+            self.clock_mode.frame_delta(real_delta, self.speed)
+        } else {
+            (0.0, 0)
+        };
+        delta += self.pending_steps as f32 * self.clock_mode.step_duration();
+        frames += self.pending_steps;
+        self.pending_steps = 0;
+
+        let ClockMode::FixedStep { fps } = self.clock_mode else {
+            self.fixed_time = None;
+            self.time = self.wrap(self.time as f64 + delta as f64) as f32;
+            self.frame = self.frame.saturating_add_signed(frames);
+            return delta;
+        };
+
+        let elapsed = |frame: u32| frame as f64 / fps as f64;
+        if self.fixed_time != Some(self.time) {
+            self.fixed_origin = self.time as f64 - elapsed(self.frame);
+        }
+        self.frame = self.frame.saturating_add_signed(frames);
+        self.time = self.wrap(self.fixed_origin + elapsed(self.frame)) as f32;
+        self.fixed_time = Some(self.time);
+
+        delta
+    }
+
+    fn wrap(&self, time: f64) -> f64 {
+        let [start, end] = self.loop_range.map(f64::from);
+        if self.looping && end > start && !(start..=end).contains(&time) {
+            start + (time - start).rem_euclid(end - start)
+        } else if !self.looping {
+            time.max(0.0)
+        } else {
+            time
+        }
+    }
+}
+
+#[allow(unused)]
+mod tests {
+    use crate::transport::{ClockMode, Transport, STEP_DURATION};
+
+    #[test]
+    fn paused_only_moves_by_steps() {
+        let mut transport = Transport {
+            playing: false,
+            ..Default::default()
+        };
+        transport.step(2);
+        transport.advance(1.0);
+
+        assert!(transport.time == 2.0 * STEP_DURATION);
+        assert!(transport.frame == 2);
+        assert!(transport.advance(1.0) == 0.0);
+    }
+
+    #[test]
+    fn loops_within_range() {
+        let mut transport = Transport {
+            looping: true,
+            loop_range: [1.0, 3.0],
+            time: 2.5,
+            ..Default::default()
+        };
+        transport.advance(1.0);
+
+        assert!((transport.time - 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn never_rewinds_before_zero() {
+        let mut transport = Transport {
+            speed: -2.0,
+            time: 1.0,
+            ..Default::default()
+        };
+        transport.advance(1.0);
+
+        assert!(transport.time == 0.0);
+    }
+
+    #[test]
+    fn fixed_step_ignores_wall_clock() {
+        let mut transport = Transport {
+            clock_mode: ClockMode::FixedStep { fps: 30 },
+            ..Default::default()
+        };
+        for real_delta in [0.5, 0.001, 2.0] {
+            transport.advance(real_delta);
+        }
+
+        assert!(transport.time == (3.0 / 30.0f64) as f32);
+        assert!(transport.frame == 3);
+    }
+
+    #[test]
+    fn fixed_step_doesnt_drift() {
+        let mut transport = Transport {
+            clock_mode: ClockMode::FixedStep { fps: 60 },
+            ..Default::default()
+        };
+        for _ in 0..100_000 {
+            transport.advance(0.016);
+            assert!(transport.time == (transport.frame as f64 / 60.0) as f32);
+        }
+        assert!(transport.frame == 100_000);
+    }
+
+    #[test]
+    fn fixed_step_starts_from_the_loop() {
+        let mut transport = Transport {
+            clock_mode: ClockMode::FixedStep { fps: 30 },
+            looping: true,
+            loop_range: [1.0, 100.0],
+            ..Default::default()
+        };
+        transport.reset();
+        for _ in 0..2000 {
+            transport.advance(0.016);
+            assert!(transport.time == (1.0 + transport.frame as f64 / 30.0) as f32);
+        }
+    }
+
+    #[test]
+    fn fixed_step_ignores_speed() {
+        let mut transport = Transport {
+            clock_mode: ClockMode::FixedStep { fps: 10 },
+            speed: 4.0,
+            ..Default::default()
+        };
+        transport.advance(1.0);
+
+        assert!(transport.time == 0.1);
+    }
+
+    #[test]
+    fn scrubbed_frames_only_count_steps() {
+        let mut transport = Transport {
+            clock_mode: ClockMode::Scrubbed,
+            ..Default::default()
+        };
+        transport.advance(1.0);
+        assert!(transport.frame == 0);
+
+        transport.step(1);
+        transport.advance(1.0);
+        assert!(transport.frame == 1);
+    }
+}
@@ -1,6 +1,10 @@
-use std::{path::PathBuf, time::Instant};
+use std::{
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-use egui::FullOutput;
+use egui::{text::LayoutJob, FullOutput, TextFormat};
 use egui_wgpu_backend::ScreenDescriptor;
 use egui_winit_platform::{Platform, PlatformDescriptor};
 use wgpu::{CommandEncoder, TextureView};
@@ -11,19 +15,24 @@ use winit::{
 };
 
 use super::{
-    shader::Uniform,
-    wgsl::{RuntimeStruct, PType, StructSlotOptions, TType},
+    editor::{tokenize, Editor, TokenKind},
+    explore::Explorer,
+    expr::Expressions,
+    history::History,
+    morph::{Morph, Snapshot},
+    presets::Presets,
+    shader::{ShaderFileBuf, Uniform},
+    shadertoy_json::Import,
+    sweep::{Sweep, SweepAxis, MAX_STEPS},
+    targets::{Target, Targets},
+    timeline::{Interpolation, Keyframe, Timeline},
+    transport::{ClockMode, Transport, FIXED_FPS},
+    wgsl::{PType, RuntimeStruct, StructLayout, TType},
 };
 
 fn make_f32(ui: &mut egui::Ui, gui_struct: &mut RuntimeStruct, slot: usize) {
     let identifier = gui_struct.slots[slot - 1].identifier.clone();
-    let range = gui_struct.slots[slot - 1]
-        .options
-        .as_ref()
-        .map_or(0.0..=1.0, |x| {
-            let StructSlotOptions::Slider { range } = x;
-            range.to_owned()
-        });
+    let range = gui_struct.slots[slot - 1].range().unwrap_or(0.0..=1.0);
     let data = gui_struct.read_from_slot_ref_mut::<f32>(slot);
     ui.horizontal(|ui| {
         ui.add(egui::widgets::Slider::new(data, range));
@@ -33,14 +42,10 @@ fn make_f32(ui: &mut egui::Ui, gui_struct: &mut RuntimeStruct, slot: usize) {
 
 fn make_u32(ui: &mut egui::Ui, gui_struct: &mut RuntimeStruct, slot: usize) {
     let identifier = gui_struct.slots[slot - 1].identifier.clone();
-    let range = gui_struct.slots[slot - 1]
-        .options
-        .as_ref()
-        .map_or(0..=100, |x| {
-            let StructSlotOptions::Slider { range } = x;
-            let (s, e) = (*range.start(), *range.end());
-            s as _..=e as _
-        });
+    let range = gui_struct.slots[slot - 1].range().map_or(0..=100, |range| {
+        let (s, e) = (*range.start(), *range.end());
+        s as _..=e as _
+    });
     let data = gui_struct.read_from_slot_ref_mut::<u32>(slot);
     ui.horizontal(|ui| {
         ui.add(egui::widgets::Slider::new(data, range));
@@ -48,6 +53,24 @@ fn make_u32(ui: &mut egui::Ui, gui_struct: &mut RuntimeStruct, slot: usize) {
     });
 }
 
+fn make_vec2f32(ui: &mut egui::Ui, gui_struct: &mut RuntimeStruct, slot: usize) {
+    let identifier = gui_struct.slots[slot - 1].identifier.clone();
+    let range = gui_struct.slots[slot - 1].range();
+    let data = gui_struct.read_from_slot_ref_mut::<[f32; 2]>(slot);
+    ui.horizontal(|ui| {
+        for x in data {
+            let drag = match &range {
+                Some(range) => egui::DragValue::new(x)
+                    .clamp_range(range.clone())
+                    .speed((range.end() - range.start()) / 200.0),
+                None => egui::DragValue::new(x).speed(0.01),
+            };
+            ui.add(drag);
+        }
+        ui.label(identifier);
+    });
+}
+
 fn make_vec3f32(ui: &mut egui::Ui, gui_struct: &mut RuntimeStruct, slot: usize) {
     let identifier = gui_struct.slots[slot - 1].identifier.clone();
     let data = gui_struct.read_from_slot_ref_mut::<[f32; 3]>(slot);
@@ -91,35 +114,998 @@ fn make_vec4u32(ui: &mut egui::Ui, gui_struct: &mut RuntimeStruct, slot: usize)
     gui_struct.write_to_slot::<[u32; 4]>(slot, &data.map(|x| (x * 255.0) as _));
 }
 
-pub fn generate_auto_ui(ctx: &egui::Context, gui_struct: &mut RuntimeStruct) {
+fn preset_bar(
+    ui: &mut egui::Ui,
+    presets: &mut Presets,
+    gui_struct: &mut RuntimeStruct,
+    history: &mut History,
+) {
+    let mut selected = None;
+    egui::ComboBox::from_id_source("presets")
+        .selected_text(presets.selected().unwrap_or("no preset"))
+        .show_ui(ui, |ui| {
+            for name in presets.names() {
+                if ui
+                    .selectable_label(presets.selected() == Some(name), name)
+                    .clicked()
+                {
+                    selected = Some(name.to_owned());
+                }
+            }
+        });
+    if let Some(name) = selected {
+        history.label_next(format!("preset {name}"));
+        presets.select(&name, gui_struct);
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(&mut presets.new_name).desired_width(100.0));
+        let name = presets.new_name.trim().to_owned();
+        if ui
+            .add_enabled(!name.is_empty(), egui::Button::new("Save"))
+            .on_hover_text("Save the current values, replaces a preset of the same name")
+            .clicked()
+        {
+            presets.save(&name, gui_struct);
+            presets.new_name.clear();
+        }
+        if let Some(name) = presets.selected().map(str::to_owned) {
+            if ui
+                .button("Delete")
+                .on_hover_text(format!("Delete {name}"))
+                .clicked()
+            {
+                presets.delete(&name);
+            }
+        }
+    });
+}
+
+fn snapshot_picker(
+    ui: &mut egui::Ui,
+    label: &str,
+    end: &mut Option<Snapshot>,
+    presets: &Presets,
+    gui_struct: &RuntimeStruct,
+) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        egui::ComboBox::from_id_source(("morph", label))
+            .selected_text(end.as_ref().map_or("pick...", |s| s.label.as_str()))
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(false, "current values").clicked() {
+                    *end = Some(Snapshot {
+                        label: "current values".to_owned(),
+                        values: gui_struct.values(),
+                    });
+                }
+                for name in presets.names() {
+                    let selected = end.as_ref().is_some_and(|s| s.label == name);
+                    if ui.selectable_label(selected, name).clicked() {
+                        *end = presets.get(name).map(|values| Snapshot {
+                            label: name.to_owned(),
+                            values: values.clone(),
+                        });
+                    }
+                }
+            });
+    });
+}
+
+fn morph_panel(
+    ui: &mut egui::Ui,
+    morph: &mut Morph,
+    presets: &Presets,
+    gui_struct: &mut RuntimeStruct,
+) {
+    egui::CollapsingHeader::new("Morph").show(ui, |ui| {
+        snapshot_picker(ui, "A", &mut morph.a, presets, gui_struct);
+        snapshot_picker(ui, "B", &mut morph.b, presets, gui_struct);
+
+        ui.add_enabled_ui(morph.is_ready(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("A");
+                if ui
+                    .add(egui::Slider::new(&mut morph.mix, 0.0..=1.0).show_value(false))
+                    .changed()
+                {
+                    morph.stop();
+                    morph.apply(gui_struct);
+                }
+                ui.label("B");
+            });
+            ui.horizontal(|ui| {
+                if ui.button("to A").clicked() {
+                    morph.transition_to(0.0);
+                }
+                if ui.button("to B").clicked() {
+                    morph.transition_to(1.0);
+                }
+                if morph.is_transitioning() && ui.button("stop").clicked() {
+                    morph.stop();
+                }
+                ui.add(
+                    egui::DragValue::new(&mut morph.duration)
+                        .speed(0.1)
+                        .clamp_range(0.0..=60.0)
+                        .suffix(" s"),
+                );
+            });
+        });
+    });
+}
+
+fn history_panel(ui: &mut egui::Ui, history: &mut History, gui_struct: &mut RuntimeStruct) {
+    egui::CollapsingHeader::new("History").show(ui, |ui| {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(history.can_undo(), egui::Button::new("Undo"))
+                .on_hover_text("Ctrl+Z")
+                .clicked()
+            {
+                history.undo(gui_struct);
+            }
+            if ui
+                .add_enabled(history.can_redo(), egui::Button::new("Redo"))
+                .on_hover_text("Ctrl+Shift+Z")
+                .clicked()
+            {
+                history.redo(gui_struct);
+            }
+        });
+
+        let mut jump = None;
+        egui::ScrollArea::vertical()
+            .max_height(150.0)
+            .show(ui, |ui| {
+                if ui
+                    .selectable_label(history.position() == 0, "initial values")
+                    .clicked()
+                {
+                    jump = Some(0);
+                }
+                for (i, label) in history.labels().enumerate() {
+                    // greyed out once undone, until redone
+                    let text = if i < history.position() {
+                        egui::RichText::new(label)
+                    } else {
+                        egui::RichText::new(label).weak()
+                    };
+                    if ui
+                        .selectable_label(history.position() == i + 1, text)
+                        .clicked()
+                    {
+                        jump = Some(i + 1);
+                    }
+                }
+            });
+        if let Some(position) = jump {
+            history.jump_to(position, gui_struct);
+        }
+    });
+}
+
+fn explore_panel(
+    ui: &mut egui::Ui,
+    explorer: &mut Explorer,
+    gui_struct: &mut RuntimeStruct,
+    history: &mut History,
+) {
+    egui::CollapsingHeader::new("Explore").show(ui, |ui| {
+        ui.horizontal(|ui| {
+            if ui
+                .button("Randomize")
+                .on_hover_text("Random values within each slot's range, 🔒 slots are kept")
+                .clicked()
+            {
+                history.label_next("randomize");
+                explorer.randomize(gui_struct);
+            }
+            if ui
+                .button("Mutate")
+                .on_hover_text("Nudge the current values, 🔒 slots are kept")
+                .clicked()
+            {
+                history.label_next("mutate");
+                explorer.mutate(gui_struct);
+            }
+            ui.add(
+                egui::Slider::new(&mut explorer.amount, 0.0..=1.0)
+                    .show_value(false)
+                    .text("amount"),
+            );
+        });
+
+        let mut restore = None;
+        ui.horizontal_wrapped(|ui| {
+            for (i, variant) in explorer.variants().iter().enumerate() {
+                if ui.small_button(format!("#{}", i + 1)).clicked() {
+                    restore = Some((i, variant.clone()));
+                }
+            }
+        });
+        if let Some((i, variant)) = restore {
+            history.label_next(format!("variant #{}", i + 1));
+            gui_struct.apply_values(&variant);
+        }
+    });
+}
+
+fn sweep_axis_editor(
+    ui: &mut egui::Ui,
+    label: &str,
+    axis: &mut Option<SweepAxis>,
+    gui_struct: &RuntimeStruct,
+) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        egui::ComboBox::from_id_source(("sweep", label))
+            .selected_text(axis.as_ref().map_or("none", |a| a.slot.as_str()))
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(axis.is_none(), "none").clicked() {
+                    *axis = None;
+                }
+                let scalars = gui_struct.slots.iter().filter(|s| {
+                    use PType::*;
+                    matches!(s.typed, TType::Scalar(F16 | F32 | I32 | U32))
+                });
+                for slot in scalars {
+                    let selected = axis.as_ref().is_some_and(|a| a.slot == slot.identifier);
+                    if ui.selectable_label(selected, &slot.identifier).clicked() {
+                        let range = slot.range().unwrap_or(0.0..=1.0);
+                        *axis = Some(SweepAxis {
+                            slot: slot.identifier.clone(),
+                            range: [*range.start(), *range.end()],
+                            steps: 5,
+                        });
+                    }
+                }
+            });
+
+        if let Some(axis) = axis {
+            ui.add(egui::DragValue::new(&mut axis.range[0]).speed(0.01));
+            ui.add(egui::DragValue::new(&mut axis.range[1]).speed(0.01));
+            ui.add(
+                egui::DragValue::new(&mut axis.steps)
+                    .clamp_range(1..=MAX_STEPS)
+                    .suffix(" steps"),
+            );
+        }
+    });
+}
+
+/// Returns true when the contact sheet should be rendered
+fn sweep_panel(ui: &mut egui::Ui, sweep: &mut Sweep, gui_struct: &RuntimeStruct) -> bool {
+    egui::CollapsingHeader::new("Sweep")
+        .show(ui, |ui| {
+            sweep_axis_editor(ui, "x", &mut sweep.x, gui_struct);
+            sweep_axis_editor(ui, "y", &mut sweep.y, gui_struct);
+            ui.horizontal(|ui| {
+                let [width, height] = &mut sweep.thumbnail_size;
+                ui.add(egui::DragValue::new(width).clamp_range(16..=1024));
+                ui.label("x");
+                ui.add(egui::DragValue::new(height).clamp_range(16..=1024));
+                ui.label("thumbnails");
+            });
+            ui.add_enabled(
+                sweep.x.is_some() || sweep.y.is_some(),
+                egui::Button::new("Render contact sheet"),
+            )
+            .on_hover_text("Saved as a png next to the shader")
+            .clicked()
+        })
+        .body_returned
+        .unwrap_or(false)
+}
+
+fn history_hotkeys(ctx: &egui::Context, history: &mut History, gui_struct: &mut RuntimeStruct) {
+    // text fields have their own undo
+    if ctx.wants_keyboard_input() {
+        return;
+    }
+    let (undo, redo) = ctx.input(|i| {
+        let z = i.modifiers.command && i.key_pressed(egui::Key::Z);
+        (z && !i.modifiers.shift, z && i.modifiers.shift)
+    });
+
+    if undo {
+        history.undo(gui_struct);
+    }
+    if redo {
+        history.redo(gui_struct);
+    }
+}
+
+fn expression_editor(ui: &mut egui::Ui, expressions: &mut Expressions, name: &str) {
+    let Some(mut source) = expressions.get(name).map(|b| b.source.clone()) else {
+        return;
+    };
+
+    ui.horizontal(|ui| {
+        if ui
+            .small_button("✕")
+            .on_hover_text("Back to manual control")
+            .clicked()
+        {
+            expressions.remove(name);
+            return;
+        }
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut source)
+                .code_editor()
+                .desired_width(160.0),
+        );
+        ui.label(name);
+        if response.changed() {
+            expressions.set(name, &source);
+        }
+    });
+    if let Some(error) = expressions.get(name).and_then(|b| b.error()) {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+}
+
+/// `header` draws the tools shown above the slot widgets
+pub fn generate_auto_ui(
+    ctx: &egui::Context,
+    gui_struct: &mut RuntimeStruct,
+    expressions: &mut Expressions,
+    explorer: &mut Explorer,
+    header: impl FnOnce(&mut egui::Ui, &mut RuntimeStruct, &mut Explorer),
+) {
     egui::SidePanel::right("autogen_ui").show(ctx, |ui| {
         use egui::*;
         trace!(ui);
 
+        header(ui, gui_struct, explorer);
+        ui.add(widgets::Separator::default());
+
         for i in 0..gui_struct.slots.len() {
             let actual_slot = i + 1;
+            let identifier = gui_struct.slots[i].identifier.clone();
 
-            use TType::*;
-            use PType::*;
-            match &gui_struct.slots[i].typed {
-                Scalar(F32) => make_f32(ui, gui_struct, actual_slot),
-                Scalar(U32) => make_u32(ui, gui_struct, actual_slot),
-                Vector(3, F32) => make_vec3f32(ui, gui_struct, actual_slot),
-                Vector(4, F32) => make_vec4f32(ui, gui_struct, actual_slot),
-                Vector(3, U32) => make_vec3u32(ui, gui_struct, actual_slot),
-                Vector(4, U32) => make_vec4u32(ui, gui_struct, actual_slot),
-                _ => {}
+            if expressions.get(&identifier).is_some() {
+                expression_editor(ui, expressions, &identifier);
+                ui.add(widgets::Separator::default());
+                continue;
             }
 
+            ui.horizontal(|ui| {
+                let locked = explorer.is_locked(&identifier);
+                if ui
+                    .selectable_label(locked, if locked { "🔒" } else { "🔓" })
+                    .on_hover_text("Locked slots aren't randomized nor mutated")
+                    .clicked()
+                {
+                    explorer.toggle_lock(&identifier);
+                }
+                if let Some(value) = gui_struct.slot_value(actual_slot) {
+                    if ui
+                        .small_button("ƒ")
+                        .on_hover_text("Drive with an expression, e.g. 0.5 + 0.5*sin(time*2)")
+                        .clicked()
+                    {
+                        let components = value.components().iter().map(f64::to_string);
+                        expressions.set(&identifier, &components.collect::<Vec<_>>().join(", "));
+                    }
+                }
+
+                use PType::*;
+                use TType::*;
+                match &gui_struct.slots[i].typed {
+                    Scalar(F32) => make_f32(ui, gui_struct, actual_slot),
+                    Scalar(U32) => make_u32(ui, gui_struct, actual_slot),
+                    Vector(2, F32) => make_vec2f32(ui, gui_struct, actual_slot),
+                    Vector(3, F32) => make_vec3f32(ui, gui_struct, actual_slot),
+                    Vector(4, F32) => make_vec4f32(ui, gui_struct, actual_slot),
+                    Vector(3, U32) => make_vec3u32(ui, gui_struct, actual_slot),
+                    Vector(4, U32) => make_vec4u32(ui, gui_struct, actual_slot),
+                    _ => {
+                        ui.label(&identifier);
+                    }
+                }
+            });
+
             ui.add(widgets::Separator::default());
         }
     });
 }
 
+fn clock_mode_selector(ui: &mut egui::Ui, clock_mode: &mut ClockMode) {
+    let label = |mode: &ClockMode| match mode {
+        ClockMode::RealTime => "real time",
+        ClockMode::FixedStep { .. } => "fixed step",
+        ClockMode::Scrubbed => "scrubbed",
+    };
+    let fixed_fps = match clock_mode {
+        ClockMode::FixedStep { fps } => *fps,
+        _ => 60,
+    };
+
+    egui::ComboBox::from_id_source("clock_mode")
+        .selected_text(label(clock_mode))
+        .show_ui(ui, |ui| {
+            for mode in [
+                ClockMode::RealTime,
+                ClockMode::FixedStep { fps: fixed_fps },
+                ClockMode::Scrubbed,
+            ] {
+                let selected = label(&mode) == label(clock_mode);
+                if ui.selectable_label(selected, label(&mode)).clicked() {
+                    *clock_mode = mode;
+                }
+            }
+        });
+    if let ClockMode::FixedStep { fps } = clock_mode {
+        ui.add(
+            egui::DragValue::new(fps)
+                .clamp_range(FIXED_FPS)
+                .suffix(" fps"),
+        );
+    }
+}
+
+fn transport_bar(ui: &mut egui::Ui, transport: &mut Transport) {
+    ui.horizontal(|ui| {
+        clock_mode_selector(ui, &mut transport.clock_mode);
+        let play_label = if transport.playing { "⏸" } else { "▶" };
+        if ui
+            .button(play_label)
+            .on_hover_text("Play/pause (Alt+Up)")
+            .clicked()
+        {
+            transport.toggle_play();
+        }
+        if ui.button("⏮").on_hover_text("Reset (Alt+Down)").clicked() {
+            transport.reset();
+        }
+        if ui
+            .button("-1")
+            .on_hover_text("Step back (Alt+Left)")
+            .clicked()
+        {
+            transport.step(-1);
+        }
+        if ui
+            .button("+1")
+            .on_hover_text("Step forward (Alt+Right)")
+            .clicked()
+        {
+            transport.step(1);
+        }
+
+        let range = if transport.looping {
+            transport.loop_range[0]..=transport.loop_range[1]
+        } else {
+            0.0..=transport.time.max(10.0).ceil()
+        };
+        ui.add(egui::Slider::new(&mut transport.time, range).text("time"));
+        ui.label(format!("frame {}", transport.frame));
+        ui.add(
+            egui::DragValue::new(&mut transport.speed)
+                .speed(0.01)
+                .clamp_range(-8.0..=8.0)
+                .prefix("speed x"),
+        );
+
+        ui.checkbox(&mut transport.looping, "loop");
+        if transport.looping {
+            let [start, end] = &mut transport.loop_range;
+            ui.add(
+                egui::DragValue::new(start)
+                    .speed(0.1)
+                    .clamp_range(0.0..=*end),
+            );
+            ui.add(
+                egui::DragValue::new(end)
+                    .speed(0.1)
+                    .clamp_range(*start..=f32::MAX),
+            );
+        }
+        ui.checkbox(&mut transport.reset_on_reload, "reset on reload");
+    });
+}
+
+fn transport_hotkeys(ctx: &egui::Context, transport: &mut Transport) {
+    use egui::Key::*;
+
+    let (toggle, reset, back, forward) = ctx.input(|i| {
+        let alt_and = |key| i.modifiers.alt && i.key_pressed(key);
+        (
+            alt_and(ArrowUp),
+            alt_and(ArrowDown),
+            alt_and(ArrowLeft),
+            alt_and(ArrowRight),
+        )
+    });
+
+    if toggle {
+        transport.toggle_play();
+    }
+    if reset {
+        transport.reset();
+    }
+    if back {
+        transport.step(-1);
+    }
+    if forward {
+        transport.step(1);
+    }
+}
+
+fn interpolation_selector(ui: &mut egui::Ui, interpolation: &mut Interpolation) {
+    let label = |interpolation: &Interpolation| match interpolation {
+        Interpolation::Linear => "linear",
+        Interpolation::Ease => "ease",
+        Interpolation::Bezier(_) => "bezier",
+    };
+    let handles = match interpolation {
+        Interpolation::Bezier(handles) => *handles,
+        _ => [0.42, 0.0, 0.58, 1.0],
+    };
+
+    egui::ComboBox::from_id_source("key_interpolation")
+        .selected_text(label(interpolation))
+        .show_ui(ui, |ui| {
+            for mode in [
+                Interpolation::Linear,
+                Interpolation::Ease,
+                Interpolation::Bezier(handles),
+            ] {
+                let selected = label(&mode) == label(interpolation);
+                if ui.selectable_label(selected, label(&mode)).clicked() {
+                    *interpolation = mode;
+                }
+            }
+        });
+    if let Interpolation::Bezier(handles) = interpolation {
+        for (i, handle) in handles.iter_mut().enumerate() {
+            // x of the control points must stay in [0, 1] for the curve to be a function of time
+            let range = if i % 2 == 0 { 0.0..=1.0 } else { -2.0..=3.0 };
+            ui.add(egui::DragValue::new(handle).speed(0.01).clamp_range(range));
+        }
+    }
+}
+
+fn key_editor(ui: &mut egui::Ui, timeline: &mut Timeline) {
+    let Some((name, index)) = timeline.selected.clone() else {
+        ui.label("Click a key to edit it, ◆ keys the current value at the playhead");
+        return;
+    };
+    let Some(mut key) = timeline.keys(&name).get(index).cloned() else {
+        timeline.selected = None;
+        return;
+    };
+
+    ui.horizontal(|ui| {
+        ui.label(format!("{name} key"));
+        let mut changed = ui
+            .add(
+                egui::DragValue::new(&mut key.time)
+                    .speed(0.01)
+                    .clamp_range(0.0..=f32::MAX)
+                    .prefix("at "),
+            )
+            .changed();
+        let before = key.interpolation;
+        interpolation_selector(ui, &mut key.interpolation);
+        changed |= before != key.interpolation;
+
+        if ui.button("Delete").clicked() {
+            timeline.remove_key(&name, index);
+        } else if changed {
+            timeline.remove_key(&name, index);
+            let index = timeline.set_key(&name, key);
+            timeline.selected = Some((name, index));
+        }
+    });
+}
+
+fn timeline_panel(
+    ctx: &egui::Context,
+    timeline: &mut Timeline,
+    gui_struct: &RuntimeStruct,
+    transport: &mut Transport,
+) {
+    use egui::*;
+
+    const KEY_RADIUS: f32 = 5.0;
+
+    TopBottomPanel::bottom("timeline").show(ctx, |ui| {
+        key_editor(ui, timeline);
+        ui.separator();
+
+        let end = [
+            timeline.end_time(),
+            transport.time,
+            transport.loop_range[1],
+            10.0,
+        ]
+        .into_iter()
+        .fold(0.0, f32::max)
+        .ceil();
+
+        for slot in 1..=gui_struct.slots.len() {
+            let Some(value) = gui_struct.slot_value(slot) else {
+                continue;
+            };
+            let name = gui_struct.slots[slot - 1].identifier.clone();
+
+            ui.horizontal(|ui| {
+                if ui
+                    .small_button("◆")
+                    .on_hover_text("Key the current value at the playhead")
+                    .clicked()
+                {
+                    let index = timeline.set_key(
+                        &name,
+                        Keyframe {
+                            time: transport.time,
+                            value,
+                            interpolation: Interpolation::Linear,
+                        },
+                    );
+                    timeline.selected = Some((name.clone(), index));
+                }
+                ui.add_sized([100.0, 18.0], Label::new(&name));
+
+                let (rect, response) =
+                    ui.allocate_exact_size(vec2(ui.available_width(), 18.0), Sense::click());
+                let x_of = |time: f32| rect.left() + rect.width() * time / end;
+                let painter = ui.painter_at(rect);
+                painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+                for (i, key) in timeline.keys(&name).iter().enumerate() {
+                    let center = pos2(x_of(key.time), rect.center().y);
+                    let selected = timeline.selected == Some((name.clone(), i));
+                    let color = if selected {
+                        ui.visuals().selection.bg_fill
+                    } else {
+                        ui.visuals().text_color()
+                    };
+                    painter.add(Shape::convex_polygon(
+                        vec![
+                            center + vec2(0.0, -KEY_RADIUS),
+                            center + vec2(KEY_RADIUS, 0.0),
+                            center + vec2(0.0, KEY_RADIUS),
+                            center + vec2(-KEY_RADIUS, 0.0),
+                        ],
+                        color,
+                        Stroke::NONE,
+                    ));
+                }
+                let playhead = x_of(transport.time);
+                painter.vline(
+                    playhead,
+                    rect.y_range(),
+                    Stroke::new(1.0, Color32::LIGHT_RED),
+                );
+
+                // clicking a key selects it, clicking elsewhere moves the playhead
+                if let Some(pos) = response
+                    .interact_pointer_pos()
+                    .filter(|_| response.clicked())
+                {
+                    let hit = timeline
+                        .keys(&name)
+                        .iter()
+                        .position(|key| (x_of(key.time) - pos.x).abs() <= KEY_RADIUS);
+                    match hit {
+                        Some(i) => timeline.selected = Some((name.clone(), i)),
+                        None => transport.time = (pos.x - rect.left()) / rect.width() * end,
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn token_color(kind: TokenKind, visuals: &egui::Visuals) -> egui::Color32 {
+    use egui::Color32;
+
+    match kind {
+        TokenKind::Plain => visuals.text_color(),
+        TokenKind::Keyword => Color32::from_rgb(255, 100, 100),
+        TokenKind::Type => Color32::from_rgb(109, 147, 226),
+        TokenKind::Attribute => Color32::from_rgb(220, 170, 90),
+        TokenKind::Number => Color32::from_rgb(87, 165, 171),
+        TokenKind::Comment => Color32::GRAY,
+    }
+}
+
+/// Highlighted WGSL, with the background of the erroneous line tinted
+fn highlight_wgsl(ui: &egui::Ui, source: &str, error_line: Option<usize>) -> LayoutJob {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let error_range = error_line
+        .and_then(|line| {
+            let start = source
+                .split_inclusive('\n')
+                .take(line.saturating_sub(1))
+                .map(str::len)
+                .sum();
+            let len = source[start..].find('\n').unwrap_or(source.len() - start);
+            (start < source.len()).then_some(start..start + len)
+        })
+        .unwrap_or_default();
+    let error_background = ui.visuals().error_fg_color.linear_multiply(0.25);
+
+    let mut job = LayoutJob::default();
+    for (kind, range) in tokenize(source) {
+        // split tokens at the error line bounds
+        let mut bounds = vec![range.start, range.end];
+        for bound in [error_range.start, error_range.end] {
+            if range.contains(&bound) && bound != range.start {
+                bounds.insert(bounds.len() - 1, bound);
+            }
+        }
+        for part in bounds.windows(2) {
+            let in_error = error_range.contains(&part[0]);
+            let format = TextFormat {
+                font_id: font_id.clone(),
+                color: token_color(kind, ui.visuals()),
+                background: if in_error {
+                    error_background
+                } else {
+                    egui::Color32::TRANSPARENT
+                },
+                ..Default::default()
+            };
+            job.append(&source[part[0]..part[1]], 0.0, format);
+        }
+    }
+    job.wrap.max_width = f32::INFINITY;
+    job
+}
+
+fn line_gutter(ui: &mut egui::Ui, lines: RangeInclusive<usize>, error_line: Option<usize>) {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let mut gutter = LayoutJob::default();
+    for line in lines {
+        let color = if Some(line) == error_line {
+            ui.visuals().error_fg_color
+        } else {
+            ui.visuals().weak_text_color()
+        };
+        let text = format!("{line:>4}\n");
+        gutter.append(&text, 0.0, TextFormat::simple(font_id.clone(), color));
+    }
+    ui.label(gutter);
+}
+
+/// What the last Shadertoy import wrote and its notes, or why it failed
+fn import_panel(ui: &mut egui::Ui, import: &Result<Import, String>) {
+    let import = match import {
+        Ok(import) => import,
+        Err(e) => {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+            return;
+        }
+    };
+    for path in &import.written {
+        ui.label(format!("Wrote {}", path.display()));
+    }
+    if !import.notes.is_empty() {
+        ui.separator();
+    }
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for note in &import.notes {
+            ui.label(note);
+        }
+    });
+}
+
+/// Read-only composed source, split in its segments, with the uniform layouts
+fn generated_panel(ui: &mut egui::Ui, source: &ShaderFileBuf, layouts: [(&str, &StructLayout); 2]) {
+    use egui::*;
+
+    ScrollArea::vertical().show(ui, |ui| {
+        CollapsingHeader::new("Layout")
+            .default_open(true)
+            .show(ui, |ui| {
+                for (name, layout) in layouts {
+                    ui.label(format!(
+                        "{name}: size {}, align {}",
+                        layout.size, layout.align
+                    ));
+                    Grid::new(name).striped(true).show(ui, |ui| {
+                        for header in ["field", "type", "offset", "size", "align"] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+                        for field in &layout.fields {
+                            ui.monospace(&field.identifier);
+                            ui.monospace(&field.typed);
+                            ui.monospace(field.offset.to_string());
+                            ui.monospace(field.size.to_string());
+                            ui.monospace(field.align.to_string());
+                            ui.end_row();
+                        }
+                    });
+                }
+            });
+
+        for (i, (name, range)) in source.segments().into_iter().enumerate() {
+            let mut text = &source.as_ref()[range.clone()];
+            let first_line = source.as_ref()[..range.start].matches('\n').count() + 1;
+            let last_line = first_line + text.lines().count().saturating_sub(1);
+            CollapsingHeader::new(format!("{name}  (lines {first_line}-{last_line})"))
+                .id_source(("generated_segment", i))
+                .default_open(name != "std")
+                .show(ui, |ui| {
+                    ui.horizontal_top(|ui| {
+                        line_gutter(ui, first_line..=last_line, None);
+                        let mut layouter = |ui: &Ui, text: &str, _wrap_width: f32| {
+                            let job = highlight_wgsl(ui, text, None);
+                            ui.fonts(|fonts| fonts.layout_job(job))
+                        };
+                        ui.add(
+                            TextEdit::multiline(&mut text)
+                                .code_editor()
+                                .margin(vec2(4.0, 0.0))
+                                .desired_width(f32::INFINITY)
+                                .desired_rows(1)
+                                .layouter(&mut layouter),
+                        );
+                    });
+                });
+        }
+    });
+}
+
+fn targets_panel(
+    ui: &mut egui::Ui,
+    targets: &mut Targets,
+    source: &ShaderFileBuf,
+    shader_path: &Path,
+) {
+    use egui::*;
+
+    let selected = *targets.selected.get_or_insert(Target::Glsl);
+    ui.horizontal(|ui| {
+        for target in Target::ALL {
+            if ui
+                .selectable_label(selected == target, target.name())
+                .clicked()
+            {
+                targets.selected = Some(target);
+            }
+        }
+    });
+    ui.separator();
+
+    let outputs = match targets.outputs(source, selected) {
+        Ok(outputs) => outputs,
+        Err(e) => {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+            return;
+        }
+    };
+    ScrollArea::vertical().show(ui, |ui| {
+        for (i, output) in outputs.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.strong(&output.label);
+                let path = output.export_path(shader_path);
+                if ui
+                    .button("Export")
+                    .on_hover_text(path.to_string_lossy())
+                    .clicked()
+                {
+                    output.export(shader_path);
+                }
+            });
+            ui.push_id(i, |ui| {
+                ui.add(
+                    TextEdit::multiline(&mut output.text.as_str())
+                        .code_editor()
+                        .desired_width(f32::INFINITY),
+                );
+            });
+        }
+    });
+}
+
+/// Returns true when the buffer should be recompiled
+fn editor_panel(ctx: &egui::Context, editor: &mut Editor) -> bool {
+    use egui::*;
+
+    SidePanel::left("editor")
+        .resizable(true)
+        .default_width(520.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let name = editor
+                    .path()
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned();
+                ui.label(if editor.is_dirty() {
+                    format!("{name} •")
+                } else {
+                    name
+                });
+                let save_requested = ui.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::S));
+                if ui
+                    .add_enabled(editor.is_dirty(), Button::new("Save"))
+                    .on_hover_text("Ctrl+S")
+                    .clicked()
+                    || save_requested
+                {
+                    editor.save();
+                }
+            });
+            if editor.changed_on_disk() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(ui.visuals().warn_fg_color, "Changed on disk");
+                    if ui.button("Reload").clicked() {
+                        editor.reload();
+                    }
+                    if ui.button("Keep mine").clicked() {
+                        editor.keep_edits();
+                    }
+                });
+            }
+            if let Some(diagnostic) = &editor.diagnostic {
+                let summary = diagnostic.message.lines().next().unwrap_or_default();
+                let summary = match diagnostic.line {
+                    Some(line) => format!("line {line}: {summary}"),
+                    None => summary.to_owned(),
+                };
+                ui.colored_label(ui.visuals().error_fg_color, summary)
+                    .on_hover_text(RichText::new(&diagnostic.message).monospace());
+            }
+            ui.separator();
+
+            let error_line = editor.diagnostic.as_ref().and_then(|d| d.line);
+            ScrollArea::both().show(ui, |ui| {
+                ui.horizontal_top(|ui| {
+                    let line_count = editor.buffer.split('\n').count();
+                    line_gutter(ui, 1..=line_count, error_line);
+
+                    let mut layouter = |ui: &Ui, text: &str, _wrap_width: f32| {
+                        let job = highlight_wgsl(ui, text, error_line);
+                        ui.fonts(|fonts| fonts.layout_job(job))
+                    };
+                    let response = ui.add(
+                        TextEdit::multiline(&mut editor.buffer)
+                            .code_editor()
+                            .lock_focus(true)
+                            .margin(vec2(4.0, 0.0))
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(40)
+                            .layouter(&mut layouter),
+                    );
+                    if response.changed() {
+                        editor.edited();
+                    }
+                });
+            });
+        });
+
+    editor.compile_due()
+}
+
 #[derive(Debug)]
 pub enum ShadeyEvent {
     OpenFileDialog,
     ReloadShader(PathBuf),
+    /// Write the passes of a Shadertoy JSON export and open its Image pass
+    ImportShadertoy(PathBuf),
+    /// Compile unsaved source in place of the current shader
+    CompileSource(String),
+    FramesDecoded {
+        /// Which load of the textures the frames were decoded for
+        generation: u64,
+        name: String,
+        frames: Vec<image::RgbaImage>,
+    },
+    RenderContactSheet(Sweep),
+    /// Write the shader as a standalone WebGPU page next to it
+    ExportWebPage {
+        controls: bool,
+    },
+    /// Write the shader with its current values as constants, next to it
+    ExportFrozen,
 }
 
 unsafe impl Sync for ShadeyEvent {}
@@ -130,6 +1116,23 @@ pub struct Egui {
     previous_frame_time: Option<f32>,
     event_loop_proxy: EventLoopProxy<ShadeyEvent>,
     pub gui_uniform: Uniform,
+    pub presets: Presets,
+    morph: Morph,
+    pub timeline: Timeline,
+    pub editor: Editor,
+    /// The last composed source that compiled, and the std struct layout
+    generated: Option<ShaderFileBuf>,
+    pub std_layout: StructLayout,
+    show_generated: bool,
+    targets: Targets,
+    web_controls: bool,
+    pub expressions: Expressions,
+    pub history: History,
+    explorer: Explorer,
+    sweep: Sweep,
+    pub transport: Transport,
+    /// The last Shadertoy import, shown until closed
+    pub import_report: Option<Result<Import, String>>,
     draw_right_panel: bool,
 }
 
@@ -140,6 +1143,8 @@ impl Egui {
         config_format: wgpu::TextureFormat,
         event_loop: &EventLoop<ShadeyEvent>,
         gui_uniform: Uniform,
+        presets: Presets,
+        shader_path: &Path,
     ) -> Self {
         let PhysicalSize {
             height: physical_height,
@@ -160,6 +1165,21 @@ impl Egui {
             previous_frame_time: None,
             event_loop_proxy: event_loop.create_proxy(),
             gui_uniform,
+            presets,
+            morph: Morph::default(),
+            timeline: Timeline::for_shader(shader_path),
+            editor: Editor::open(shader_path),
+            generated: None,
+            std_layout: StructLayout::default(),
+            show_generated: false,
+            targets: Targets::default(),
+            web_controls: true,
+            expressions: Expressions::default(),
+            history: History::default(),
+            explorer: Explorer::default(),
+            sweep: Sweep::default(),
+            transport: Transport::default(),
+            import_report: None,
             draw_right_panel: true,
         }
     }
@@ -194,6 +1214,15 @@ impl Egui {
             .unwrap();
     }
 
+    pub fn generated(&self) -> Option<&ShaderFileBuf> {
+        self.generated.as_ref()
+    }
+
+    pub fn shader_compiled(&mut self, source: ShaderFileBuf) {
+        self.generated = Some(source);
+        self.targets.invalidate();
+    }
+
     fn update(&mut self, ctx: &egui::Context) {
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             use egui::*;
@@ -206,13 +1235,114 @@ impl Egui {
                             .ok()
                             .unwrap();
                     }
+                    ui.checkbox(&mut self.show_generated, "Generated source");
+                    ui.checkbox(&mut self.targets.visible, "Cross-compiled output");
+                    ui.separator();
+                    if ui.button("Export web page").clicked() {
+                        self.event_loop_proxy
+                            .send_event(ShadeyEvent::ExportWebPage {
+                                controls: self.web_controls,
+                            })
+                            .ok()
+                            .unwrap();
+                    }
+                    ui.checkbox(&mut self.web_controls, "with a control panel");
+                    if ui.button("Export frozen WGSL").clicked() {
+                        self.event_loop_proxy
+                            .send_event(ShadeyEvent::ExportFrozen)
+                            .ok()
+                            .unwrap();
+                    }
                 });
-                ui.add_space(ui.available_width() - 90.0);
+                ui.add_space(ui.available_width() - 250.0);
+                ui.checkbox(&mut self.editor.visible, " Editor");
+                ui.checkbox(&mut self.timeline.visible, " Timeline");
                 ui.checkbox(&mut self.draw_right_panel, " Draw Panel?");
             });
+            transport_bar(ui, &mut self.transport);
         });
+        if let Some(generated) = self.generated.as_ref().filter(|_| self.show_generated) {
+            let gui_layout = self.gui_uniform.runtime_struct.layout();
+            egui::Window::new("Generated source")
+                .open(&mut self.show_generated)
+                .default_size([640.0, 480.0])
+                .show(ctx, |ui| {
+                    generated_panel(
+                        ui,
+                        generated,
+                        [
+                            ("StdUniform", &self.std_layout),
+                            ("GuiControlled", &gui_layout),
+                        ],
+                    )
+                });
+        }
+        if let Some(generated) = self.generated.as_ref() {
+            let mut visible = self.targets.visible;
+            egui::Window::new("Cross-compiled output")
+                .open(&mut visible)
+                .default_size([640.0, 480.0])
+                .show(ctx, |ui| {
+                    targets_panel(ui, &mut self.targets, generated, self.editor.path())
+                });
+            self.targets.visible &= visible;
+        }
+        if let Some(import) = &self.import_report {
+            let mut open = true;
+            egui::Window::new("Shadertoy import")
+                .open(&mut open)
+                .show(ctx, |ui| import_panel(ui, import));
+            if !open {
+                self.import_report = None;
+            }
+        }
+        transport_hotkeys(ctx, &mut self.transport);
+        if self.morph.tick(ctx.input(|i| i.stable_dt)) {
+            self.morph.apply(&mut self.gui_uniform.runtime_struct);
+        }
+        history_hotkeys(ctx, &mut self.history, &mut self.gui_uniform.runtime_struct);
+
+        // whatever changes between here and the end of the frame comes from the ui
+        let before = self.gui_uniform.runtime_struct.values();
+        if self.editor.visible && editor_panel(ctx, &mut self.editor) {
+            self.event_loop_proxy
+                .send_event(ShadeyEvent::CompileSource(self.editor.buffer.clone()))
+                .ok()
+                .unwrap();
+        }
+        if self.timeline.visible {
+            timeline_panel(
+                ctx,
+                &mut self.timeline,
+                &self.gui_uniform.runtime_struct,
+                &mut self.transport,
+            );
+        }
         if self.draw_right_panel {
-            generate_auto_ui(ctx, &mut self.gui_uniform.runtime_struct);
+            let mut render_sweep = false;
+            generate_auto_ui(
+                ctx,
+                &mut self.gui_uniform.runtime_struct,
+                &mut self.expressions,
+                &mut self.explorer,
+                |ui, gui_struct, explorer| {
+                    preset_bar(ui, &mut self.presets, gui_struct, &mut self.history);
+                    morph_panel(ui, &mut self.morph, &self.presets, gui_struct);
+                    explore_panel(ui, explorer, gui_struct, &mut self.history);
+                    history_panel(ui, &mut self.history, gui_struct);
+                    render_sweep = sweep_panel(ui, &mut self.sweep, gui_struct);
+                },
+            );
+            if render_sweep {
+                self.event_loop_proxy
+                    .send_event(ShadeyEvent::RenderContactSheet(self.sweep.clone()))
+                    .ok()
+                    .unwrap();
+            }
         }
+
+        let after = self.gui_uniform.runtime_struct.values();
+        let gesture_ongoing = ctx.input(|i| i.pointer.any_down()) || ctx.wants_keyboard_input();
+        self.history.record(&before, &after, gesture_ongoing);
     }
 }
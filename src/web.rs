@@ -0,0 +1,283 @@
+//! The current shader as a standalone page: the composed WGSL, the textures
+//! embedded as PNG data URLs and the `GuiControlled` values, rendered by a
+//! small WebGPU harness (`web.html`) feeding `StdUniform` like shadey does.
+
+use std::{
+    collections::BTreeMap,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use super::{
+    parser::ShaderOptions,
+    shader::ShaderFileBuf,
+    texture::Texture,
+    wgsl::{PType, RuntimeStruct, StructLayout, StructSlotOptions, TType},
+};
+
+const HARNESS: &str = include_str!("web.html");
+
+#[derive(Serialize)]
+struct Config<'a> {
+    title: &'a str,
+    wgsl: &'a str,
+    std: StdConfig,
+    gui: GuiConfig,
+    textures: Vec<TextureConfig>,
+    controls: bool,
+}
+
+#[derive(Serialize)]
+struct StdConfig {
+    size: usize,
+    offsets: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize)]
+struct GuiConfig {
+    initial: Vec<u8>,
+    slots: Vec<SlotControl>,
+}
+
+/// A control of the generated panel, `kind` picks the input element
+#[derive(Serialize)]
+struct SlotControl {
+    name: String,
+    kind: &'static str,
+    offset: usize,
+    min: f32,
+    max: f32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TextureConfig {
+    dimension: &'static str,
+    // none binds a transparent placeholder
+    url: Option<String>,
+    address_mode_u: &'static str,
+    address_mode_v: &'static str,
+}
+
+/// Next to the shader, `voronoi.wgsl` -> `voronoi.html`
+pub fn export_path(shader_path: &Path) -> PathBuf {
+    shader_path.with_extension("html")
+}
+
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, b)| acc | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+fn address_mode(mode: &Option<wgpu::AddressMode>) -> &'static str {
+    match mode {
+        Some(wgpu::AddressMode::Repeat) => "repeat",
+        Some(wgpu::AddressMode::MirrorRepeat) => "mirror-repeat",
+        _ => "clamp-to-edge",
+    }
+}
+
+/// Image files are re-encoded as PNG, flipped already when asked to
+fn png_data_url(path: &Path, vflip: bool) -> Option<String> {
+    let image = image::open(path).ok()?;
+    let image = match vflip {
+        true => image.flipv(),
+        false => image,
+    };
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .ok()?;
+    Some(format!("data:image/png;base64,{}", base64(&png)))
+}
+
+fn texture_config(
+    texture: &Texture,
+    options: &[ShaderOptions],
+    notes: &mut Vec<String>,
+) -> TextureConfig {
+    let name = &texture.name;
+    let dimension = match texture.view_dimension {
+        wgpu::TextureViewDimension::Cube => "cube",
+        wgpu::TextureViewDimension::D3 => "3d",
+        wgpu::TextureViewDimension::D2Array => "2d-array",
+        _ => "2d",
+    };
+    let placeholder = TextureConfig {
+        dimension,
+        url: None,
+        address_mode_u: "clamp-to-edge",
+        address_mode_v: "clamp-to-edge",
+    };
+
+    let option = options.iter().find(|option| option.name() == Some(name));
+    let Some(ShaderOptions::Texture {
+        path,
+        u_addr_mode,
+        v_addr_mode,
+        vflip,
+        ..
+    }) = option
+    else {
+        notes.push(format!(
+            "{name}: only image textures are exported, a placeholder is bound"
+        ));
+        return placeholder;
+    };
+    if texture.audio.is_some() || texture.view_dimension == wgpu::TextureViewDimension::D2Array {
+        notes.push(format!(
+            "{name}: animated textures aren't exported, a placeholder is bound"
+        ));
+        return placeholder;
+    }
+    let Some(url) = png_data_url(path, *vflip) else {
+        notes.push(format!("{name}: {path:?} couldn't be embedded"));
+        return placeholder;
+    };
+
+    TextureConfig {
+        url: Some(url),
+        address_mode_u: address_mode(u_addr_mode),
+        address_mode_v: address_mode(v_addr_mode),
+        ..placeholder
+    }
+}
+
+/// Controls for the slots shadey has widgets for, with the same ranges
+fn slot_controls(gui_struct: &RuntimeStruct, notes: &mut Vec<String>) -> Vec<SlotControl> {
+    let layout = gui_struct.layout();
+    gui_struct
+        .slots
+        .iter()
+        .zip(layout.fields)
+        .filter_map(|(slot, field)| {
+            if let Some(StructSlotOptions::Expression { .. }) = slot.options {
+                notes.push(format!(
+                    "{}: expressions aren't exported, the current value is kept",
+                    slot.identifier
+                ));
+            }
+            use PType::*;
+            use TType::*;
+            let (kind, range) = match slot.typed {
+                Scalar(F32) => ("f32", slot.range().unwrap_or(0.0..=1.0)),
+                Scalar(U32) => ("u32", slot.range().unwrap_or(0.0..=100.0)),
+                Vector(2, F32) => ("vec2", slot.range().unwrap_or(0.0..=1.0)),
+                Vector(3, F32) => ("rgb", 0.0..=1.0),
+                Vector(4, F32) => ("rgba", 0.0..=1.0),
+                Vector(3, U32) => ("rgb8", 0.0..=255.0),
+                Vector(4, U32) => ("rgba8", 0.0..=255.0),
+                _ => return None,
+            };
+            Some(SlotControl {
+                name: slot.identifier.clone(),
+                kind,
+                offset: field.offset,
+                min: *range.start(),
+                max: *range.end(),
+            })
+        })
+        .collect()
+}
+
+/// The page, and notes on what couldn't be exported. Textures are in bind
+/// group order, as the composed source declares them.
+pub fn page(
+    title: &str,
+    source: &ShaderFileBuf,
+    std_layout: &StructLayout,
+    gui_struct: &RuntimeStruct,
+    textures: &[Texture],
+    options: &[ShaderOptions],
+    controls: bool,
+) -> (String, Vec<String>) {
+    let mut notes = Vec::new();
+    let config = Config {
+        title,
+        wgsl: source.as_ref(),
+        std: StdConfig {
+            size: std_layout.size,
+            offsets: std_layout
+                .fields
+                .iter()
+                .map(|field| (field.identifier.clone(), field.offset))
+                .collect(),
+        },
+        gui: GuiConfig {
+            initial: gui_struct.buffer().to_vec(),
+            slots: slot_controls(gui_struct, &mut notes),
+        },
+        textures: textures
+            .iter()
+            .map(|texture| texture_config(texture, options, &mut notes))
+            .collect(),
+        controls,
+    };
+
+    // a json string can't end the script it's in
+    let config = serde_json::to_string(&config)
+        .expect("Page config is serializable")
+        .replace("</", "<\\/");
+    let title = title
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let page = HARNESS
+        .replace("{{TITLE}}", &title)
+        .replace("/*{{CONFIG}}*/", &config);
+    (page, notes)
+}
+
+#[allow(unused)]
+mod tests {
+    use crate::{
+        shader::compose_source,
+        web::{base64, page},
+        wgsl::StructLayout,
+    };
+
+    #[test]
+    fn base64_padding() {
+        assert!(base64(b"").is_empty());
+        assert!(base64(b"f") == "Zg==");
+        assert!(base64(b"fo") == "Zm8=");
+        assert!(base64(b"foo") == "Zm9v");
+        assert!(base64(b"shadey") == "c2hhZGV5");
+    }
+
+    #[test]
+    fn page_embeds_shader_and_values() {
+        let source = "struct GuiControlled {\r\n    speed: f32, // range(min=0,max=4) default(2)\r\n};\r\n\r\n// </script>\r\n";
+        let (shader, gui_struct) = compose_source(source).unwrap();
+
+        let (html, notes) = page(
+            "speed",
+            &shader,
+            &StructLayout::default(),
+            &gui_struct,
+            &[],
+            &[],
+            true,
+        );
+        assert!(notes.is_empty());
+        assert!(html.contains("<title>speed</title>"));
+        assert!(html.contains(r#""initial":[0,0,0,64]"#));
+        assert!(html.contains(r#"{"name":"speed","kind":"f32","offset":0,"min":0.0,"max":4.0}"#));
+        assert!(html.matches("</script>").count() == 1);
+    }
+}
@@ -1,4 +1,6 @@
-use std::ops::RangeInclusive;
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PType {
@@ -122,9 +124,35 @@ fn offset_of_member(struc: &Vec<StructSlot>, slot: usize) -> usize {
     round_up(k, n)
 }
 
+/// The components of a slot widened to f64, how slot values get serialized
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SlotValue {
+    Scalar(f64),
+    Vector(Vec<f64>),
+}
+
+impl SlotValue {
+    pub fn components(&self) -> &[f64] {
+        match self {
+            SlotValue::Scalar(x) => std::slice::from_ref(x),
+            SlotValue::Vector(xs) => xs,
+        }
+    }
+}
+
+/// Slot values by slot name, so they can be applied to an edited struct
+pub type SlotValues = BTreeMap<String, SlotValue>;
+
 #[derive(Debug, PartialEq)]
 pub enum StructSlotOptions {
-    Slider { range: RangeInclusive<f32> },
+    Slider {
+        range: RangeInclusive<f32>,
+    },
+    /// Evaluated every frame instead of being edited, see `expr.rs`
+    Expression {
+        source: String,
+    },
 }
 
 #[derive(Debug, PartialEq)]
@@ -132,9 +160,18 @@ pub struct StructSlot {
     pub identifier: String,
     pub typed: TType,
     pub options: Option<StructSlotOptions>,
+    /// Value the slot starts with, zero otherwise
+    pub default: Option<SlotValue>,
 }
 
 impl StructSlot {
+    pub fn range(&self) -> Option<RangeInclusive<f32>> {
+        match &self.options {
+            Some(StructSlotOptions::Slider { range }) => Some(range.to_owned()),
+            _ => None,
+        }
+    }
+
     pub fn generate_definition(&self) -> String {
         format!(
             "fn {ident}() -> {typed} {{return _gui.{ident};}}",
@@ -144,6 +181,23 @@ impl StructSlot {
     }
 }
 
+/// Where a slot sits in its uniform buffer
+#[derive(Debug, PartialEq)]
+pub struct FieldLayout {
+    pub identifier: String,
+    pub typed: String,
+    pub offset: usize,
+    pub size: usize,
+    pub align: usize,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct StructLayout {
+    pub fields: Vec<FieldLayout>,
+    pub size: usize,
+    pub align: usize,
+}
+
 #[derive(Debug)]
 pub struct RuntimeStruct {
     pub slots: Vec<StructSlot>,
@@ -154,11 +208,25 @@ impl RuntimeStruct {
     pub fn new(slots: Vec<StructSlot>) -> Self {
         let mut buffer = Vec::with_capacity(slots.size());
         buffer.resize_with(slots.size(), Default::default);
-        Self { slots, buffer }
+        let mut runtime_struct = Self { slots, buffer };
+
+        for slot in 1..=runtime_struct.slots.len() {
+            let Some(default) = runtime_struct.slots[slot - 1].default.clone() else {
+                continue;
+            };
+            if !runtime_struct.set_slot_value(slot, &default) {
+                let identifier = &runtime_struct.slots[slot - 1].identifier;
+                eprintln!("Default of {identifier} doesn't fit its slot, ignored");
+            }
+        }
+        runtime_struct
     }
 
     pub fn write_to_slot<T: bytemuck::Pod>(&mut self, slot: usize, data: &T) {
-        let data_bufer = bytemuck::bytes_of(data);
+        self.write_bytes_to_slot(slot, bytemuck::bytes_of(data));
+    }
+
+    pub fn write_bytes_to_slot(&mut self, slot: usize, data_bufer: &[u8]) {
         let slot_offset = offset_of_member(&self.slots, slot);
         self.buffer[slot_offset..data_bufer.len() + slot_offset].copy_from_slice(data_bufer);
     }
@@ -177,6 +245,89 @@ impl RuntimeStruct {
         bytemuck::from_bytes_mut(&mut self.buffer[slot_offset..slot_offset + size_to_read])
     }
 
+    /// None for the types that can't be (de)serialized: matrices, arrays, 64 bits
+    pub fn slot_value(&self, slot: usize) -> Option<SlotValue> {
+        let (count, ptype) = value_components(&self.slots[slot - 1].typed)?;
+        let slot_offset = offset_of_member(&self.slots, slot);
+        let bytes = &self.buffer[slot_offset..slot_offset + count * ptype.size()];
+
+        let mut components = bytes.chunks_exact(ptype.size()).map(|c| match ptype {
+            PType::F16 => half::f16::from_bits(bytemuck::pod_read_unaligned(c)).to_f64(),
+            PType::F32 => bytemuck::pod_read_unaligned::<f32>(c) as f64,
+            PType::I32 => bytemuck::pod_read_unaligned::<i32>(c) as f64,
+            _ => bytemuck::pod_read_unaligned::<u32>(c) as f64,
+        });
+        Some(match self.slots[slot - 1].typed {
+            TType::Scalar(_) => SlotValue::Scalar(components.next()?),
+            _ => SlotValue::Vector(components.collect()),
+        })
+    }
+
+    /// Integers are rounded and clamped to their range.
+    /// Returns false, leaving the slot untouched, when the value doesn't fit the slot type.
+    pub fn set_slot_value(&mut self, slot: usize, value: &SlotValue) -> bool {
+        let Some((count, ptype)) = value_components(&self.slots[slot - 1].typed) else {
+            return false;
+        };
+        if value.components().len() != count {
+            return false;
+        }
+
+        let mut bytes = Vec::with_capacity(count * ptype.size());
+        for x in value.components() {
+            match ptype {
+                PType::F16 => bytes.extend(half::f16::from_f64(*x).to_bits().to_ne_bytes()),
+                PType::F32 => bytes.extend((*x as f32).to_ne_bytes()),
+                PType::I32 => bytes.extend((x.round() as i32).to_ne_bytes()),
+                _ => bytes.extend((x.round() as u32).to_ne_bytes()),
+            }
+        }
+        self.write_bytes_to_slot(slot, &bytes);
+        true
+    }
+
+    pub fn values(&self) -> SlotValues {
+        (1..=self.slots.len())
+            .filter_map(|slot| {
+                let value = self.slot_value(slot)?;
+                Some((self.slots[slot - 1].identifier.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Matched by slot name, values of missing slots or of slots whose type changed are skipped
+    pub fn apply_values(&mut self, values: &SlotValues) {
+        for (name, value) in values {
+            let Some(slot) = self.get_slot_number(name) else {
+                continue;
+            };
+            if !self.set_slot_value(slot, value) {
+                eprintln!("Value of {name} doesn't fit its slot anymore, skipped");
+            }
+        }
+    }
+
+    pub fn layout(&self) -> StructLayout {
+        let fields = (1..=self.slots.len())
+            .map(|slot| {
+                let typed = &self.slots[slot - 1].typed;
+                FieldLayout {
+                    identifier: self.slots[slot - 1].identifier.clone(),
+                    typed: typed.into(),
+                    offset: offset_of_member(&self.slots, slot),
+                    size: typed.size(),
+                    align: typed.align(),
+                }
+            })
+            .collect();
+
+        StructLayout {
+            fields,
+            size: self.slots.size(),
+            align: self.slots.align(),
+        }
+    }
+
     pub fn buffer(&self) -> &[u8] {
         &self.buffer[..]
     }
@@ -186,6 +337,15 @@ impl RuntimeStruct {
     }
 }
 
+fn value_components(typed: &TType) -> Option<(usize, PType)> {
+    match typed {
+        TType::Scalar(x) => Some((1, *x)),
+        TType::Vector(n, x) => Some((*n, *x)),
+        _ => None,
+    }
+    .filter(|(_, x)| matches!(x, PType::F16 | PType::F32 | PType::I32 | PType::U32))
+}
+
 impl Sized for Vec<StructSlot> {
     fn size(&self) -> usize {
         let n = offset_of_member(self, self.len()) + self.last().unwrap().typed.size();
@@ -201,7 +361,9 @@ impl Aligned for Vec<StructSlot> {
 
 #[allow(unused)]
 mod tests {
-    use crate::wgsl::{Aligned, PType, Sized, TType};
+    use crate::wgsl::{
+        Aligned, PType, RuntimeStruct, Sized, SlotValue, SlotValues, StructSlot, TType,
+    };
     use PType::*;
     use TType::*;
 
@@ -352,4 +514,82 @@ mod tests {
             }
         }
     }
+
+    fn slot(identifier: &str, typed: TType) -> StructSlot {
+        StructSlot {
+            identifier: identifier.to_owned(),
+            typed,
+            options: None,
+            default: None,
+        }
+    }
+
+    #[test]
+    fn slot_values_roundtrip() {
+        let mut gui_struct = RuntimeStruct::new(vec![
+            slot("speed", Scalar(F32)),
+            slot("color", Vector(3, F32)),
+            slot("count", Scalar(U32)),
+        ]);
+        gui_struct.write_to_slot(1, &0.5f32);
+        gui_struct.write_to_slot(2, &[1.0f32, 0.25, 0.0]);
+        gui_struct.write_to_slot(3, &7u32);
+
+        let values = gui_struct.values();
+        assert!(values["speed"] == SlotValue::Scalar(0.5));
+        assert!(values["color"] == SlotValue::Vector(vec![1.0, 0.25, 0.0]));
+        assert!(values["count"] == SlotValue::Scalar(7.0));
+
+        let mut other = RuntimeStruct::new(vec![
+            slot("count", Scalar(U32)),
+            slot("speed", Scalar(F32)),
+            slot("color", Vector(3, F32)),
+        ]);
+        other.apply_values(&values);
+        assert!(other.values() == values);
+    }
+
+    #[test]
+    fn mismatched_values_are_skipped() {
+        let mut gui_struct = RuntimeStruct::new(vec![
+            slot("color", Vector(4, F32)),
+            slot("count", Scalar(I32)),
+        ]);
+        let values = SlotValues::from([
+            ("color".to_owned(), SlotValue::Vector(vec![1.0, 1.0, 1.0])),
+            ("count".to_owned(), SlotValue::Scalar(-2.6)),
+            ("gone".to_owned(), SlotValue::Scalar(1.0)),
+        ]);
+        gui_struct.apply_values(&values);
+
+        assert!(gui_struct.slot_value(1) == Some(SlotValue::Vector(vec![0.0; 4])));
+        assert!(gui_struct.slot_value(2) == Some(SlotValue::Scalar(-3.0)));
+    }
+
+    #[test]
+    fn defaults_are_applied() {
+        let mut speed = slot("speed", Scalar(F32));
+        speed.default = Some(SlotValue::Scalar(0.5));
+        let mut color = slot("color", Vector(3, F32));
+        color.default = Some(SlotValue::Vector(vec![1.0, 0.0]));
+        let gui_struct = RuntimeStruct::new(vec![speed, color]);
+
+        assert!(gui_struct.slot_value(1) == Some(SlotValue::Scalar(0.5)));
+        assert!(gui_struct.slot_value(2) == Some(SlotValue::Vector(vec![0.0; 3])));
+    }
+
+    #[test]
+    fn layout_table() {
+        let layout = RuntimeStruct::new(vec![
+            slot("speed", Scalar(F32)),
+            slot("color", Vector(3, F32)),
+            slot("count", Scalar(U32)),
+        ])
+        .layout();
+        let offsets = layout.fields.iter().map(|f| f.offset).collect::<Vec<_>>();
+
+        assert!(offsets == [0, 16, 28]);
+        assert!(layout.fields[1].typed == "vec3<f32>");
+        assert!(layout.size == 32 && layout.align == 16);
+    }
 }